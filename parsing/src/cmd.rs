@@ -1,4 +1,5 @@
-use nom::{digit, rest_s};
+use nom::types::CompleteStr;
+use nom::{anychar, digit, multispace, rest_s};
 use std::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -27,6 +28,35 @@ impl<'a> Command<'a> {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitution<'a> {
+    pub pattern: &'a str,
+    pub replacement: &'a str,
+    pub global: bool,
+}
+
+impl<'a> Substitution<'a> {
+    pub fn new(pattern: &'a str, replacement: &'a str, global: bool) -> Substitution<'a> {
+        Substitution {
+            pattern,
+            replacement,
+            global,
+        }
+    }
+
+    /// Applies the substitution to `text`, returning `None` if the pattern does not occur
+    pub fn apply(&self, text: &str) -> Option<String> {
+        if !text.contains(self.pattern) {
+            return None;
+        }
+        if self.global {
+            Some(text.replace(self.pattern, self.replacement))
+        } else {
+            Some(text.replacen(self.pattern, self.replacement, 1))
+        }
+    }
+}
+
 named!(pub number(&str) -> usize, map_res!(digit, FromStr::from_str));
 
 named!(fixed(&str) -> Endpoint,
@@ -86,6 +116,42 @@ named!(
     )
 );
 
+named!(
+    substitution(&str) -> Substitution,
+    do_parse!(
+        char!('s') >>
+        char!('/') >>
+        pattern: take_until1!("/") >>
+        char!('/') >>
+        replacement: take_until!("/") >>
+        char!('/') >>
+        flags: verify!(rest_s, |flags: &str| flags.is_empty() || flags == "g") >>
+        (Substitution::new(pattern, replacement, flags == "g"))
+    )
+);
+
+named!(quoted_arg<CompleteStr, String>,
+    delimited!(
+        char!('"'),
+        map!(
+            many0!(alt!(preceded!(char!('\\'), anychar) | none_of!("\"\\"))),
+            |chars| chars.into_iter().collect()
+        ),
+        char!('"')
+    )
+);
+
+named!(bare_arg<CompleteStr, String>,
+    map!(is_not!(" \t\""), |arg| arg.0.to_owned())
+);
+
+named!(args<CompleteStr, Vec<String>>,
+    terminated!(
+        many0!(preceded!(opt!(multispace), alt!(quoted_arg | bare_arg))),
+        terminated!(opt!(multispace), eof!())
+    )
+);
+
 pub fn parse_cmd(cmd: &str) -> Option<Command> {
     match command(cmd) {
         Ok((_, cmd)) => Some(cmd),
@@ -93,10 +159,29 @@ pub fn parse_cmd(cmd: &str) -> Option<Command> {
     }
 }
 
+/// Splits command arguments on whitespace, keeping `"quoted strings"` together
+///
+/// Returns `None` if a quote is left unterminated
+pub fn split_args(input: &str) -> Option<Vec<String>> {
+    match args(CompleteStr(input)) {
+        Ok((_, args)) => Some(args),
+        _ => None,
+    }
+}
+
+/// Parses the body of a `s/pattern/replacement/[g]` command, without its range
+pub fn parse_substitution(cmd: &str) -> Option<Substitution> {
+    match substitution(cmd) {
+        Ok((_, sub)) => Some(sub),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::command;
     use super::Command;
+    use super::Substitution;
+    use super::{command, parse_substitution, split_args};
     use super::Endpoint::*;
     use super::Range::*;
 
@@ -178,4 +263,83 @@ mod tests {
 
         assert_eq!(result, Command::new(None, "d b/ar/"));
     }
+
+    #[test]
+    fn substitution_cmd() {
+        let result = command("s/teh/the/").unwrap().1;
+
+        assert_eq!(result, Command::new(None, "s/teh/the/"));
+        assert_eq!(
+            parse_substitution(result.command),
+            Some(Substitution::new("teh", "the", false))
+        );
+    }
+
+    #[test]
+    fn ranged_global_substitution_cmd() {
+        let result = command("/deploy/s/staging/prod/g").unwrap().1;
+
+        assert_eq!(result.range, Some(Single(Search("deploy"))));
+        assert_eq!(
+            parse_substitution(result.command),
+            Some(Substitution::new("staging", "prod", true))
+        );
+    }
+
+    #[test]
+    fn substitution_empty_replacement() {
+        assert_eq!(
+            parse_substitution("s/foo//"),
+            Some(Substitution::new("foo", "", false))
+        );
+    }
+
+    #[test]
+    fn invalid_substitutions() {
+        assert_eq!(parse_substitution("s//bar/"), None);
+        assert_eq!(parse_substitution("s/foo/bar"), None);
+        assert_eq!(parse_substitution("s/foo/bar/x"), None);
+        assert_eq!(parse_substitution("setchannel 1234"), None);
+    }
+
+    #[test]
+    fn apply_substitution() {
+        let once = Substitution::new("a", "b", false);
+        let global = Substitution::new("a", "b", true);
+
+        assert_eq!(once.apply("aaa"), Some("baa".to_owned()));
+        assert_eq!(global.apply("aaa"), Some("bbb".to_owned()));
+        assert_eq!(once.apply("ccc"), None);
+    }
+
+    #[test]
+    fn split_plain_args() {
+        assert_eq!(
+            split_args("nick  Big Bird "),
+            Some(vec!["nick".to_owned(), "Big".to_owned(), "Bird".to_owned()])
+        );
+    }
+
+    #[test]
+    fn split_quoted_args() {
+        assert_eq!(
+            split_args(r#"nick "Big Bird" "say \"hi\"""#),
+            Some(vec![
+                "nick".to_owned(),
+                "Big Bird".to_owned(),
+                r#"say "hi""#.to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn split_empty_args() {
+        assert_eq!(split_args(""), Some(vec![]));
+        assert_eq!(split_args("   "), Some(vec![]));
+    }
+
+    #[test]
+    fn split_unterminated_quote() {
+        assert_eq!(split_args(r#"nick "Big Bird"#), None);
+    }
 }
@@ -2,7 +2,7 @@
 extern crate nom;
 
 pub mod cmd;
-pub use cmd::parse_cmd;
+pub use cmd::{parse_cmd, parse_substitution, split_args};
 
 pub mod markdown;
 pub use markdown::parse_msg;
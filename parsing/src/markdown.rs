@@ -7,93 +7,317 @@ Bold Italics	        ***bold italics***
 underline bold italics	__***underline bold italics***__
 Underline	            __underline__
 Strikethrough	         ~~Strikethrough~~
+Spoiler                 ||spoiler||
+Inline code             `code` or ``code``
+Code block              ```lang\ncode```
+Block quote             > quote or >>> rest of message
+Links                   [text](https://url), <https://url> or https://url
+Escapes                 \*not italic\*
+Mentions                <@user>, <@!user>, <#channel>, <@&role>
+Timestamps              <t:seconds> or <t:seconds:style>
 */
 
-use nom::anychar;
 use nom::types::CompleteStr;
+use nom::{anychar, digit, IResult};
 
-// TODO: Support nested styles
+/// A node in the tree of a parsed message
+///
+/// Styles hold their children, so `**bold _and italic_**` nests an `Italic` inside a `Bold`
 #[derive(Debug, PartialEq)]
-pub enum Style {
+pub enum Node {
     Text(String),
     Code(String),
-    Bold(String),
-    Italic(String),
-    BoldItalics(String),
-    Underline(String),
-    UnderlineBold(String),
-    UnderlineItalics(String),
-    UnderlineBoldItalics(String),
-    Strikethrough(String),
+    CodeBlock {
+        language: Option<String>,
+        code: String,
+    },
+    Bold(Vec<Node>),
+    Italic(Vec<Node>),
+    Underline(Vec<Node>),
+    Strikethrough(Vec<Node>),
+    Spoiler(Vec<Node>),
+    BlockQuote(Vec<Node>),
+    Link {
+        text: String,
+        url: String,
+    },
+    UserMention(u64),
+    ChannelMention(u64),
+    RoleMention(u64),
+    Timestamp {
+        seconds: i64,
+        style: Option<char>,
+    },
 }
 
-use self::Style::*;
+use self::Node::*;
 
-named!(code<CompleteStr, Style>,
+/// Parses the inside of a delimited style, falling back to the literal text
+fn children(text: &str) -> Vec<Node> {
+    match nodes(CompleteStr(text)) {
+        Ok((CompleteStr(""), nodes)) => nodes,
+        _ => vec![Text(text.to_owned())],
+    }
+}
+
+/// Pushes a node, merging it with the previous one if both are text or both are block quotes
+fn push_node(nodes: &mut Vec<Node>, node: Node) {
+    match (nodes.last_mut(), node) {
+        (Some(Text(ref mut last)), Text(text)) => last.push_str(&text),
+        (Some(BlockQuote(ref mut last)), BlockQuote(quote)) => {
+            push_node(last, Text("\n".to_owned()));
+            for node in quote {
+                push_node(last, node);
+            }
+        }
+        (_, Text(ref text)) if text.is_empty() => {}
+        (_, node) => nodes.push(node),
+    }
+}
+
+fn normalize(nodes: Vec<Node>) -> Vec<Node> {
+    let mut normalized = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        push_node(&mut normalized, node);
+    }
+    normalized
+}
+
+/// Rejects inner text that is empty, padded with whitespace or ends in an escape
+fn tight(text: CompleteStr) -> bool {
+    let text = text.0;
+    !text.is_empty() && !text.starts_with(char::is_whitespace)
+        && !text.ends_with(char::is_whitespace) && !text.ends_with('\\')
+}
+
+fn code_block_node(body: &str) -> Node {
+    let (language, code) = match body.find('\n') {
+        Some(end) if end > 0 && !body[..end].contains(char::is_whitespace) => {
+            (Some(body[..end].to_owned()), &body[end + 1..])
+        }
+        Some(0) => (None, &body[1..]),
+        _ => (None, body),
+    };
+    CodeBlock {
+        language,
+        code: code.trim_right_matches('\n').to_owned(),
+    }
+}
+
+named!(code_block<CompleteStr, Node>,
+    map!(
+        delimited!(tag!("```"), take_until!("```"), tag!("```")),
+        |body| code_block_node(body.0)
+    )
+);
+
+named!(code<CompleteStr, Node>,
     map!(
-        delimited!(tag!("`"), take_until!("`"), tag!("`")),
+        alt!(
+            delimited!(tag!("``"), take_until1!("``"), tag!("``")) |
+            delimited!(tag!("`"), take_until1!("`"), tag!("`"))
+        ),
         |text| Code(text.0.to_owned())
     )
 );
 
-named!(star_italic<CompleteStr, Style>,
+named!(escape<CompleteStr, Node>,
+    map!(
+        preceded!(char!('\\'), verify!(anychar, |ch: char| ch.is_ascii_punctuation())),
+        |ch| Text(ch.to_string())
+    )
+);
+
+named!(word_char<CompleteStr, char>,
+    verify!(anychar, |ch: char| ch.is_alphanumeric())
+);
+
+// A word, including any underscores inside it, so `snake_case` is never italicised
+named!(word<CompleteStr, CompleteStr>,
+    recognize!(pair!(
+        word_char,
+        many0!(alt!(word_char | char!('_')))
+    ))
+);
+
+named!(bold_italic<CompleteStr, Node>,
     map!(
-        delimited!(tag!("*"), take_until!("*"), tag!("*")),
-        |text| Italic(text.0.to_owned())
+        delimited!(tag!("***"), verify!(take_until!("***"), tight), tag!("***")),
+        |text| Bold(vec![Italic(children(text.0))])
     )
 );
 
-named!(underscore_italic<CompleteStr, Style>,
+named!(bold<CompleteStr, Node>,
     map!(
-        delimited!(tag!("_"), take_until!("_"), tag!("_")),
-        |text| Italic(text.0.to_owned())
+        delimited!(tag!("**"), verify!(take_until!("**"), tight), tag!("**")),
+        |text| Bold(children(text.0))
     )
 );
 
-named!(italic<CompleteStr, Style>,
+named!(underline<CompleteStr, Node>,
+    map!(
+        delimited!(tag!("__"), verify!(take_until!("__"), tight), tag!("__")),
+        |text| Underline(children(text.0))
+    )
+);
+
+named!(star_italic<CompleteStr, Node>,
+    map!(
+        delimited!(tag!("*"), verify!(take_until!("*"), tight), tag!("*")),
+        |text| Italic(children(text.0))
+    )
+);
+
+named!(underscore_italic<CompleteStr, Node>,
+    map!(
+        delimited!(
+            tag!("_"),
+            verify!(take_until!("_"), tight),
+            terminated!(tag!("_"), not!(word_char))
+        ),
+        |text| Italic(children(text.0))
+    )
+);
+
+named!(italic<CompleteStr, Node>,
     alt_complete!(star_italic | underscore_italic)
 );
 
-named!(bold<CompleteStr, Style>,
+named!(strikethrough<CompleteStr, Node>,
+    map!(
+        delimited!(tag!("~~"), verify!(take_until!("~~"), tight), tag!("~~")),
+        |text| Strikethrough(children(text.0))
+    )
+);
+
+named!(spoiler<CompleteStr, Node>,
     map!(
-        delimited!(tag!("**"), take_until!("**"), tag!("**")),
-        |text| Bold(text.0.to_owned())
+        delimited!(tag!("||"), verify!(take_until!("||"), tight), tag!("||")),
+        |text| Spoiler(children(text.0))
     )
 );
 
-named!(underline<CompleteStr, Style>,
+named!(scheme<CompleteStr, CompleteStr>,
+    alt!(tag!("https://") | tag!("http://"))
+);
+
+named!(masked_link<CompleteStr, Node>,
+    do_parse!(
+        char!('[') >>
+        text: take_until1!("]") >>
+        tag!("](") >>
+        url: recognize!(pair!(scheme, is_not!(") \t\n"))) >>
+        char!(')') >>
+        (Link { text: text.0.to_owned(), url: url.0.to_owned() })
+    )
+);
+
+named!(angle_link<CompleteStr, Node>,
+    map!(
+        delimited!(
+            char!('<'),
+            recognize!(pair!(scheme, is_not!("> \t\n"))),
+            char!('>')
+        ),
+        |url| Link { text: url.0.to_owned(), url: url.0.to_owned() }
+    )
+);
+
+/// A bare url, without any trailing punctuation that most likely belongs to the sentence
+fn auto_link(input: CompleteStr) -> IResult<CompleteStr, Node> {
+    let (_, url) = recognize!(input, pair!(scheme, is_not!(" \t\n<")))?;
+    let url = url.0.trim_right_matches(|ch| ".,:;!?)'\"".contains(ch));
+    let (rest, url) = take!(input, url.len())?;
+    Ok((
+        rest,
+        Link {
+            text: url.0.to_owned(),
+            url: url.0.to_owned(),
+        },
+    ))
+}
+
+named!(link<CompleteStr, Node>,
+    alt!(masked_link | angle_link | auto_link)
+);
+
+named!(id<CompleteStr, u64>,
+    map_res!(digit, |id: CompleteStr| id.0.parse())
+);
+
+named!(timestamp<CompleteStr, Node>,
+    do_parse!(
+        tag!("<t:") >>
+        seconds: map_res!(
+            recognize!(pair!(opt!(char!('-')), digit)),
+            |seconds: CompleteStr| seconds.0.parse()
+        ) >>
+        style: opt!(preceded!(char!(':'), one_of!("tTdDfFR"))) >>
+        char!('>') >>
+        (Timestamp { seconds, style })
+    )
+);
+
+named!(mention<CompleteStr, Node>,
+    alt!(
+        map!(delimited!(tag!("<@&"), id, char!('>')), RoleMention) |
+        map!(delimited!(alt!(tag!("<@!") | tag!("<@")), id, char!('>')), UserMention) |
+        map!(delimited!(tag!("<#"), id, char!('>')), ChannelMention) |
+        timestamp
+    )
+);
+
+named!(quote_line<CompleteStr, Node>,
     map!(
-        delimited!(tag!("__"), take_until!("__"), tag!("__")),
-        |text| Underline(text.0.to_owned())
+        preceded!(tag!("> "), take_till!(|ch| ch == '\n')),
+        |text| BlockQuote(children(text.0))
     )
 );
 
-named!(strikethrough<CompleteStr, Style>,
+named!(quote_rest<CompleteStr, Node>,
     map!(
-        delimited!(tag!("~~"), take_until!("~~"), tag!("~~")),
-        |text| Strikethrough(text.0.to_owned())
+        preceded!(tag!(">>> "), take_while!(|_| true)),
+        |text| BlockQuote(children(text.0))
     )
 );
 
-named!(styled<CompleteStr, Style>, alt!(bold | underline | italic | strikethrough | code));
+named!(quote<CompleteStr, Node>, alt!(quote_rest | quote_line));
+
+// Block quotes only start at the beginning of a line, so the newline belongs to the quote
+named!(line_quote<CompleteStr, Node>, preceded!(char!('\n'), quote));
 
-named!(maybe_style<CompleteStr, Style>,
+named!(styled<CompleteStr, Node>,
     alt!(
-        styled |
-        map!(
-            many_till!(call!(anychar), alt!(recognize!(peek!(styled)) | eof!())),
-            |chars| Text(chars.0.iter().collect())
-        )
+        code_block | code | escape | line_quote | mention | link | spoiler |
+        bold_italic | bold | underline | italic | strikethrough
     )
 );
 
-named!(text<CompleteStr, Vec<Style>>,
-    many0!(maybe_style)
+named!(plain<CompleteStr, Node>,
+    map!(
+        many_till!(
+            alt!(word | recognize!(anychar)),
+            alt!(recognize!(peek!(styled)) | eof!())
+        ),
+        |(pieces, _)| Text(pieces.iter().map(|piece| piece.0).collect())
+    )
 );
 
-pub fn parse_msg(msg: &str) -> Option<Vec<Style>> {
+named!(node<CompleteStr, Node>, alt!(styled | plain));
+
+named!(nodes<CompleteStr, Vec<Node>>, map!(many0!(node), normalize));
+
+named!(text<CompleteStr, Vec<Node>>,
+    do_parse!(
+        first: opt!(quote) >>
+        rest: many0!(node) >>
+        (normalize(first.into_iter().chain(rest).collect()))
+    )
+);
+
+pub fn parse_msg(msg: &str) -> Option<Vec<Node>> {
     match text(CompleteStr(msg)) {
-        Ok((_, msg)) => Some(msg),
+        Ok((CompleteStr(""), msg)) => Some(msg),
         _ => None,
     }
 }
@@ -102,9 +326,9 @@ pub fn parse_msg(msg: &str) -> Option<Vec<Style>> {
 mod test {
     use super::*;
 
-    macro_rules! style {
-        ($style:ident($text:tt)) => {
-            $style($text.to_owned())
+    macro_rules! node {
+        ($node:ident($text:tt)) => {
+            $node($text.to_owned())
         };
     }
 
@@ -116,24 +340,24 @@ mod test {
 
     #[test]
     fn italic_underline() {
-        assert_eq!(parse!(italic("_italic_")), style!(Italic("italic")));
+        assert_eq!(parse!(italic("_italic_")), Italic(vec![node!(Text("italic"))]));
     }
 
     #[test]
     fn italic_star() {
-        assert_eq!(parse!(italic("*italic*")), style!(Italic("italic")));
+        assert_eq!(parse!(italic("*italic*")), Italic(vec![node!(Text("italic"))]));
     }
 
     #[test]
     fn bold_test() {
-        assert_eq!(parse!(bold("**bold**")), style!(Bold("bold")));
+        assert_eq!(parse!(bold("**bold**")), Bold(vec![node!(Text("bold"))]));
     }
 
     #[test]
     fn underline_test() {
         assert_eq!(
             parse!(underline("__underline__")),
-            style!(Underline("underline")),
+            Underline(vec![node!(Text("underline"))]),
         );
     }
 
@@ -141,7 +365,15 @@ mod test {
     fn strikethrough_test() {
         assert_eq!(
             parse!(strikethrough("~~strikethrough~~")),
-            style!(Strikethrough("strikethrough")),
+            Strikethrough(vec![node!(Text("strikethrough"))]),
+        );
+    }
+
+    #[test]
+    fn spoiler_test() {
+        assert_eq!(
+            parse!(spoiler("||spoiler||")),
+            Spoiler(vec![node!(Text("spoiler"))]),
         );
     }
 
@@ -152,16 +384,202 @@ mod test {
                 "_italic_ **bold** *italic* __underline__ ~~strikethrough~~"
             )),
             [
-                style!(Italic("italic")),
-                style!(Text(" ")),
-                style!(Bold("bold")),
-                style!(Text(" ")),
-                style!(Italic("italic")),
-                style!(Text(" ")),
-                style!(Underline("underline")),
-                style!(Text(" ")),
-                style!(Strikethrough("strikethrough")),
+                Italic(vec![node!(Text("italic"))]),
+                node!(Text(" ")),
+                Bold(vec![node!(Text("bold"))]),
+                node!(Text(" ")),
+                Italic(vec![node!(Text("italic"))]),
+                node!(Text(" ")),
+                Underline(vec![node!(Text("underline"))]),
+                node!(Text(" ")),
+                Strikethrough(vec![node!(Text("strikethrough"))]),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested() {
+        assert_eq!(
+            parse!(text("**bold _and italic_**")),
+            [Bold(vec![
+                node!(Text("bold ")),
+                Italic(vec![node!(Text("and italic"))]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn bold_italics() {
+        assert_eq!(
+            parse!(text("***both***")),
+            [Bold(vec![Italic(vec![node!(Text("both"))])])]
+        );
+    }
+
+    #[test]
+    fn underline_bold_italics() {
+        assert_eq!(
+            parse!(text("__***all***__")),
+            [Underline(vec![Bold(vec![Italic(vec![node!(Text("all"))])])])]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            parse!(text(r"\*not italic\* \\")),
+            [node!(Text(r"*not italic* \"))]
+        );
+    }
+
+    #[test]
+    fn snake_case() {
+        assert_eq!(
+            parse!(text("a snake_case_identifier")),
+            [node!(Text("a snake_case_identifier"))]
+        );
+    }
+
+    #[test]
+    fn unbalanced() {
+        assert_eq!(
+            parse!(text("**not bold and 2 * 3 * 4 ~~")),
+            [node!(Text("**not bold and 2 * 3 * 4 ~~"))]
+        );
+    }
+
+    #[test]
+    fn inline_code() {
+        assert_eq!(
+            parse!(text("`*code*` and ``a ` tick``")),
+            [
+                node!(Code("*code*")),
+                node!(Text(" and ")),
+                node!(Code("a ` tick")),
+            ]
+        );
+    }
+
+    #[test]
+    fn code_block_language() {
+        assert_eq!(
+            parse!(code_block("```rust\nfn main() {}\n```")),
+            CodeBlock {
+                language: Some("rust".to_owned()),
+                code: "fn main() {}".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn code_block_no_language() {
+        assert_eq!(
+            parse!(code_block("```\n**not bold**\n```")),
+            CodeBlock {
+                language: None,
+                code: "**not bold**".to_owned(),
+            }
+        );
+        assert_eq!(
+            parse!(code_block("```inline code```")),
+            CodeBlock {
+                language: None,
+                code: "inline code".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn block_quotes() {
+        assert_eq!(
+            parse!(text("> quoted *text*\n> more\nreply > not quoted")),
+            [
+                BlockQuote(vec![
+                    node!(Text("quoted ")),
+                    Italic(vec![node!(Text("text"))]),
+                    node!(Text("\nmore")),
+                ]),
+                node!(Text("\nreply > not quoted")),
+            ]
+        );
+    }
+
+    #[test]
+    fn block_quote_rest() {
+        assert_eq!(
+            parse!(text("intro\n>>> all\nof this")),
+            [
+                node!(Text("intro")),
+                BlockQuote(vec![node!(Text("all\nof this"))]),
+            ]
+        );
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            parse!(text(
+                "[docs](https://docs.rs/a_b) <https://x.io/c_d> see https://y.io/e_f_g."
+            )),
+            [
+                Link {
+                    text: "docs".to_owned(),
+                    url: "https://docs.rs/a_b".to_owned(),
+                },
+                node!(Text(" ")),
+                Link {
+                    text: "https://x.io/c_d".to_owned(),
+                    url: "https://x.io/c_d".to_owned(),
+                },
+                node!(Text(" see ")),
+                Link {
+                    text: "https://y.io/e_f_g".to_owned(),
+                    url: "https://y.io/e_f_g".to_owned(),
+                },
+                node!(Text(".")),
             ]
         );
     }
+
+    #[test]
+    fn mentions() {
+        assert_eq!(
+            parse!(text("<@1234> <@!1234> <#5678> <@&91011>")),
+            [
+                UserMention(1234),
+                node!(Text(" ")),
+                UserMention(1234),
+                node!(Text(" ")),
+                ChannelMention(5678),
+                node!(Text(" ")),
+                RoleMention(91011),
+            ]
+        );
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(
+            parse!(text("<t:1700000000:R> <t:1700000000>")),
+            [
+                Timestamp {
+                    seconds: 1700000000,
+                    style: Some('R'),
+                },
+                node!(Text(" ")),
+                Timestamp {
+                    seconds: 1700000000,
+                    style: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_mentions() {
+        assert_eq!(
+            parse!(text("<@abc> <#> <t:12:X> <@&12")),
+            [node!(Text("<@abc> <#> <t:12:X> <@&12"))]
+        );
+    }
 }
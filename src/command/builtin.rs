@@ -0,0 +1,397 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+
+use failure::Error;
+use serenity::model::channel::{Attachment, Message};
+use serenity::model::id::ChannelId;
+
+use super::range::{self, RangeError};
+use super::{notice, Arg, Command, CommandError, Invocation};
+use discord::utils;
+use helpers::size::human_size;
+use model::{expand_home, Application, Event};
+use parsing::cmd::{Range, Substitution};
+
+pub fn commands() -> Vec<Command> {
+    vec![
+        Command {
+            name: "quit",
+            aliases: &["q"],
+            args: &[],
+            ranged: false,
+            help: "Exit dex",
+            handler: quit,
+        },
+        Command {
+            name: "help",
+            aliases: &["h"],
+            args: &[Arg::Optional("command")],
+            ranged: false,
+            help: "List commands, or describe a single command",
+            handler: help,
+        },
+        Command {
+            name: "delete",
+            aliases: &["d"],
+            args: &[],
+            ranged: true,
+            help: "Delete your messages in the range, or your last message",
+            handler: delete,
+        },
+        Command {
+            name: "react",
+            aliases: &[],
+            args: &[Arg::Required("emoji")],
+            ranged: true,
+            help: "React to the messages in the range, the selected message, or the newest message",
+            handler: react,
+        },
+        Command {
+            name: "unreact",
+            aliases: &[],
+            args: &[Arg::Required("emoji")],
+            ranged: true,
+            help: "Remove your reaction from the range, the selected message, or the newest one",
+            handler: unreact,
+        },
+        Command {
+            name: "attachments",
+            aliases: &["att"],
+            args: &[],
+            ranged: false,
+            help: "List the attachments of the loaded messages, newest first",
+            handler: attachments,
+        },
+        Command {
+            name: "download",
+            aliases: &["dl"],
+            args: &[Arg::Required("number"), Arg::Optional("dir")],
+            ranged: false,
+            help: "Save an attachment listed by :attachments",
+            handler: download,
+        },
+        Command {
+            name: "upload",
+            aliases: &["up"],
+            args: &[Arg::Required("path"), Arg::OptionalRest("caption")],
+            ranged: false,
+            help: "Send a file to the current channel",
+            handler: upload,
+        },
+        Command {
+            name: "nick",
+            aliases: &[],
+            args: &[Arg::Rest("nickname")],
+            ranged: false,
+            help: "Set your nickname in the current guild",
+            handler: nick,
+        },
+        Command {
+            name: "clearnick",
+            aliases: &["cnick"],
+            args: &[],
+            ranged: false,
+            help: "Remove your nickname in the current guild",
+            handler: clear_nick,
+        },
+        Command {
+            name: "setchannel",
+            aliases: &["schan"],
+            args: &[Arg::Required("channel id")],
+            ranged: false,
+            help: "Switch to a channel by id",
+            handler: set_channel,
+        },
+        Command {
+            name: "togglesidebar",
+            aliases: &["tbar"],
+            args: &[],
+            ranged: false,
+            help: "Show or hide the guild sidebar",
+            handler: toggle_sidebar,
+        },
+    ]
+}
+
+#[derive(Fail, Debug)]
+enum BuiltinError {
+    #[fail(display = "Invalid channel id: {}", _0)]
+    InvalidChannelId(String),
+    #[fail(display = "Not in a guild")]
+    NoGuild,
+    #[fail(display = "Pattern not found: {}", _0)]
+    PatternNotFound(String),
+    #[fail(display = "No attachment numbered {} (see :attachments)", _0)]
+    InvalidAttachment(String),
+    #[fail(display = "No channel selected")]
+    NoChannel,
+}
+
+fn quit(app: &Application, _: &Invocation) -> Result<(), Error> {
+    app.context.read().event_channel.send(Event::ShutdownAll)?;
+    Ok(())
+}
+
+fn help(app: &Application, invocation: &Invocation) -> Result<(), Error> {
+    let handler = &app.command_handler;
+    match invocation.arg(0) {
+        Some(name) => {
+            let command = handler
+                .find(name)
+                .ok_or_else(|| CommandError::Unknown(name.to_owned()))?;
+            notice(app, format!(":{} - {}", command.usage(), command.help));
+            if !command.aliases.is_empty() {
+                notice(app, format!("Aliases: {}", command.aliases.join(", ")));
+            }
+        }
+        None => {
+            for command in handler.commands() {
+                notice(app, format!(":{} - {}", command.usage(), command.help));
+            }
+            notice(
+                app,
+                ":[range]s/<pattern>/<replacement>/[g] - Edit your last message, or those in the range"
+                    .to_owned(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Deletes every message of the current user inside the range
+fn delete(app: &Application, invocation: &Invocation) -> Result<(), Error> {
+    let messages = app.view.message_view.discord_messages();
+    let (start, end) = range::resolve(&invocation.range, &messages)?;
+
+    let (own, foreign): (Vec<_>, Vec<_>) =
+        messages[start..=end].iter().partition(|msg| msg.is_own());
+    if own.is_empty() {
+        return Err(RangeError::NoOwnInRange.into());
+    }
+    if !foreign.is_empty() {
+        notice(
+            app,
+            format!(
+                "Range includes {} message(s) from other users, only yours were deleted",
+                foreign.len()
+            ),
+        );
+    }
+
+    let ids: Vec<_> = own.iter().map(|msg| (msg.channel_id, msg.id)).collect();
+    let event_channel = app.context.read().event_channel.clone();
+    thread::spawn(move || {
+        for (channel_id, message_id) in ids {
+            if let Err(err) = channel_id.delete_message(message_id) {
+                event_channel
+                    .send(Event::Notice(format!("Unable to delete message: {}", err)))
+                    .unwrap();
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Edits every message of the current user inside `range` that contains the pattern
+pub fn substitute(
+    app: &Application,
+    range: &Option<Range>,
+    sub: &Substitution,
+) -> Result<(), Error> {
+    let messages = app.view.message_view.discord_messages();
+    let (start, end) = range::resolve(range, &messages)?;
+
+    let edits: Vec<_> = messages[start..=end]
+        .iter()
+        .filter(|msg| msg.is_own())
+        .filter_map(|msg| {
+            sub.apply(&msg.content)
+                .map(|content| (msg.channel_id, msg.id, content))
+        })
+        .collect();
+    if edits.is_empty() {
+        return Err(BuiltinError::PatternNotFound(sub.pattern.to_owned()))?;
+    }
+
+    let event_channel = app.context.read().event_channel.clone();
+    thread::spawn(move || {
+        for (channel_id, message_id, content) in edits {
+            if let Err(err) = channel_id.edit_message(message_id, |m| m.content(&content)) {
+                event_channel
+                    .send(Event::Notice(format!("Unable to edit message: {}", err)))
+                    .unwrap();
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Messages a command acts on: those in the range, the selected message, or the newest message
+fn targets(app: &Application, invocation: &Invocation) -> Result<Vec<Message>, Error> {
+    if invocation.range.is_some() {
+        let messages = app.view.message_view.discord_messages();
+        let (start, end) = range::resolve(&invocation.range, &messages)?;
+        return Ok(messages[start..=end].to_vec());
+    }
+    if let Some(selected) = app.view.message_view.selected_message() {
+        return Ok(vec![selected]);
+    }
+    let last = app
+        .view
+        .message_view
+        .discord_messages()
+        .pop()
+        .ok_or(RangeError::OutOfBounds(0))?;
+    Ok(vec![last])
+}
+
+fn react(app: &Application, invocation: &Invocation) -> Result<(), Error> {
+    let reaction = utils::reaction_type(invocation.arg(0).unwrap_or_default());
+    let targets = targets(app, invocation)?;
+
+    let event_channel = app.context.read().event_channel.clone();
+    thread::spawn(move || {
+        for msg in targets {
+            if let Err(err) = msg.channel_id.create_reaction(msg.id, reaction.clone()) {
+                event_channel
+                    .send(Event::Notice(format!("Unable to add reaction: {}", err)))
+                    .unwrap();
+            }
+        }
+    });
+    Ok(())
+}
+
+fn unreact(app: &Application, invocation: &Invocation) -> Result<(), Error> {
+    let reaction = utils::reaction_type(invocation.arg(0).unwrap_or_default());
+    let targets = targets(app, invocation)?;
+
+    let event_channel = app.context.read().event_channel.clone();
+    thread::spawn(move || {
+        for msg in targets {
+            if let Err(err) = msg
+                .channel_id
+                .delete_reaction(msg.id, None, reaction.clone())
+            {
+                event_channel
+                    .send(Event::Notice(format!("Unable to remove reaction: {}", err)))
+                    .unwrap();
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Attachments of the loaded messages, newest first
+fn loaded_attachments(app: &Application) -> Vec<Attachment> {
+    app.view
+        .message_view
+        .discord_messages()
+        .into_iter()
+        .rev()
+        .flat_map(|msg| msg.attachments)
+        .collect()
+}
+
+fn attachments(app: &Application, _: &Invocation) -> Result<(), Error> {
+    let attachments = loaded_attachments(app);
+    if attachments.is_empty() {
+        notice(app, "No attachments in the loaded messages".to_owned());
+    }
+    for (i, attachment) in attachments.iter().enumerate() {
+        notice(
+            app,
+            format!(
+                "{}. {} ({}) {}",
+                i + 1,
+                attachment.filename,
+                human_size(attachment.size),
+                attachment.url
+            ),
+        );
+    }
+    Ok(())
+}
+
+fn download(app: &Application, invocation: &Invocation) -> Result<(), Error> {
+    let number = invocation.arg(0).unwrap_or_default();
+    let attachment = number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|index| loaded_attachments(app).into_iter().nth(index))
+        .ok_or_else(|| BuiltinError::InvalidAttachment(number.to_owned()))?;
+    let dir = match invocation.arg(1) {
+        Some(dir) => expand_home(dir),
+        None => app.context.read().download_dir.clone(),
+    };
+
+    let event_channel = app.context.read().event_channel.clone();
+    thread::spawn(move || {
+        let save = || -> Result<PathBuf, Error> {
+            let data = attachment.download()?;
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(&attachment.filename);
+            fs::write(&path, data)?;
+            Ok(path)
+        };
+        let text = match save() {
+            Ok(path) => format!("Saved {}", path.display()),
+            Err(err) => format!("Unable to download {}: {}", attachment.filename, err),
+        };
+        event_channel.send(Event::Notice(text)).unwrap();
+    });
+    Ok(())
+}
+
+fn upload(app: &Application, invocation: &Invocation) -> Result<(), Error> {
+    let channel = app.context.read().channel.ok_or(BuiltinError::NoChannel)?;
+    let path = expand_home(invocation.arg(0).unwrap_or_default());
+    let caption = invocation.arg(1).unwrap_or_default().to_owned();
+
+    let event_channel = app.context.read().event_channel.clone();
+    thread::spawn(move || {
+        if let Err(err) = channel.send_files(vec![path.as_path()], |m| m.content(&caption)) {
+            event_channel
+                .send(Event::Notice(format!(
+                    "Unable to upload {}: {}",
+                    path.display(),
+                    err
+                )))
+                .unwrap();
+        }
+    });
+    Ok(())
+}
+
+fn nick(app: &Application, invocation: &Invocation) -> Result<(), Error> {
+    let new_nick = invocation.arg(0).unwrap_or_default();
+    debug!("Setting nickname to: {}", new_nick);
+    let guild = app.context.read().guild.ok_or(BuiltinError::NoGuild)?;
+    guild.edit_nickname(Some(new_nick))?;
+    Ok(())
+}
+
+fn clear_nick(app: &Application, _: &Invocation) -> Result<(), Error> {
+    let guild = app.context.read().guild.ok_or(BuiltinError::NoGuild)?;
+    guild.edit_nickname(None)?;
+    Ok(())
+}
+
+fn set_channel(app: &Application, invocation: &Invocation) -> Result<(), Error> {
+    let raw_id = invocation.arg(0).unwrap_or_default();
+    let channel_id: ChannelId = raw_id
+        .parse()
+        .map_err(|_| BuiltinError::InvalidChannelId(raw_id.to_owned()))?;
+    app.context
+        .read()
+        .event_channel
+        .send(Event::SetChannel(channel_id))?;
+    Ok(())
+}
+
+fn toggle_sidebar(app: &Application, _: &Invocation) -> Result<(), Error> {
+    app.toggle_sidebar();
+    Ok(())
+}
@@ -1,60 +1,222 @@
+mod builtin;
+mod range;
+
+use failure::Error;
+
 use model::Application;
 use model::Event;
-use parsing::parse_cmd;
+use parsing::cmd::Range;
+use parsing::{parse_cmd, parse_substitution, split_args};
+
+#[derive(Fail, Debug)]
+pub enum CommandError {
+    #[fail(display = "Unknown command: {} (see :help)", _0)]
+    Unknown(String),
+    #[fail(display = "Usage: :{}", _0)]
+    Usage(String),
+    #[fail(display = ":{} does not take a range", _0)]
+    UnexpectedRange(&'static str),
+    #[fail(display = "Unterminated quote")]
+    UnterminatedQuote,
+}
+
+/// A single argument in a command's signature
+pub enum Arg {
+    Required(&'static str),
+    Optional(&'static str),
+    /// Consumes every remaining word, so it must come last
+    Rest(&'static str),
+    /// Like `Rest`, but may be left out
+    OptionalRest(&'static str),
+}
+
+/// A parsed invocation of a command
+pub struct Invocation<'a> {
+    pub range: Option<Range<'a>>,
+    pub args: Vec<String>,
+}
+
+impl<'a> Invocation<'a> {
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(|arg| arg.as_str())
+    }
+}
+
+type Handler = fn(&Application, &Invocation) -> Result<(), Error>;
+
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: &'static [Arg],
+    /// Whether the command acts on a range of messages
+    pub ranged: bool,
+    pub help: &'static str,
+    handler: Handler,
+}
 
-pub struct CommandHandler {}
+impl Command {
+    pub fn usage(&self) -> String {
+        let mut usage = if self.ranged {
+            format!("[range]{}", self.name)
+        } else {
+            self.name.to_owned()
+        };
+        for arg in self.args {
+            usage += &match *arg {
+                Arg::Required(name) => format!(" <{}>", name),
+                Arg::Optional(name) => format!(" [{}]", name),
+                Arg::Rest(name) => format!(" <{}...>", name),
+                Arg::OptionalRest(name) => format!(" [{}...]", name),
+            };
+        }
+        usage
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+
+    /// Binds the split words to this command's arguments
+    fn bind(&self, mut words: Vec<String>) -> Result<Vec<String>, CommandError> {
+        let required = self
+            .args
+            .iter()
+            .filter(|arg| match arg {
+                Arg::Required(_) | Arg::Rest(_) => true,
+                Arg::Optional(_) | Arg::OptionalRest(_) => false,
+            })
+            .count();
+        let rest = match self.args.last() {
+            Some(Arg::Rest(_)) | Some(Arg::OptionalRest(_)) => true,
+            _ => false,
+        };
+
+        if words.len() < required || (!rest && words.len() > self.args.len()) {
+            return Err(CommandError::Usage(self.usage()));
+        }
+        if rest && words.len() > self.args.len() {
+            let tail = words.split_off(self.args.len() - 1).join(" ");
+            words.push(tail);
+        }
+        Ok(words)
+    }
+}
+
+pub struct CommandHandler {
+    commands: Vec<Command>,
+}
 
 impl CommandHandler {
     pub fn new() -> CommandHandler {
-        CommandHandler {}
+        CommandHandler {
+            commands: builtin::commands(),
+        }
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Command> {
+        self.commands.iter().find(|command| command.matches(name))
     }
 
-    // Todo: Add feedback when no arguments are provided
     pub fn execute(&self, app: &Application, cmd: &str) {
         debug!("Running command: {}", cmd);
-        if let Some(cmd) = parse_cmd(cmd) {
-            let split_cmd: Vec<_> = cmd.command.split_whitespace().collect();
-            match split_cmd.get(0).cloned().unwrap_or_default() {
-                "quit" | "q" => app
-                    .context
-                    .read()
-                    .event_channel
-                    .send(Event::ShutdownAll)
-                    .unwrap(),
-                "nick" => {
-                    // Nick
-                    if let Some(new_nick) = split_cmd.get(1) {
-                        debug!("Setting nickname to: {}", new_nick);
-                        app.context
-                            .read()
-                            .guild
-                            .map(|guild| guild.edit_nickname(Some(new_nick)));
-                    }
-                }
-                "clearnick" | "cnick" => {
-                    app.context
-                        .read()
-                        .guild
-                        .map(|guild| guild.edit_nickname(None));
-                }
-                "setchannel" | "schan" => if let Some(new_chan) = split_cmd.get(1) {
-                    if let Ok(new_chan_id) = new_chan.parse() {
-                        app.context
-                            .read()
-                            .event_channel
-                            .send(Event::SetChannel(new_chan_id))
-                            .unwrap()
-                    } else {
-                        // Invalid id
-                    }
-                },
-                "togglesidebar" | "tbar" => {
-                    let new_state = !app.view.message_view.showing_sidebar();
-                    app.context.write().guild_sidebar_visible = new_state;
-                    app.view.message_view.set_show_sidebar(new_state);
-                }
-                _ => {}
-            }
+        if let Err(err) = self.try_execute(app, cmd) {
+            notice(app, err.to_string());
+        }
+    }
+
+    fn try_execute(&self, app: &Application, cmd: &str) -> Result<(), Error> {
+        let cmd = match parse_cmd(cmd) {
+            Some(cmd) => cmd,
+            None => return Err(CommandError::Unknown(cmd.to_owned()))?,
+        };
+
+        if let Some(sub) = parse_substitution(cmd.command) {
+            return builtin::substitute(app, &cmd.range, &sub);
+        }
+
+        let mut words = split_args(cmd.command).ok_or(CommandError::UnterminatedQuote)?;
+        if words.is_empty() {
+            return Err(CommandError::Unknown(String::new()))?;
+        }
+        let name = words.remove(0);
+
+        let command = self
+            .find(&name)
+            .ok_or_else(|| CommandError::Unknown(name.clone()))?;
+        if cmd.range.is_some() && !command.ranged {
+            return Err(CommandError::UnexpectedRange(command.name))?;
+        }
+
+        let invocation = Invocation {
+            range: cmd.range,
+            args: command.bind(words)?,
+        };
+        (command.handler)(app, &invocation)
+    }
+}
+
+/// Shows a local notice in the message view
+fn notice(app: &Application, text: String) {
+    app.context
+        .read()
+        .event_channel
+        .send(Event::Notice(text))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn noop(_: &Application, _: &Invocation) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn command(args: &'static [Arg], ranged: bool) -> Command {
+        Command {
+            name: "test",
+            aliases: &[],
+            args,
+            ranged,
+            help: "",
+            handler: noop,
         }
     }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| (*word).to_owned()).collect()
+    }
+
+    #[test]
+    fn formats_usage() {
+        let command = command(
+            &[
+                Arg::Required("emoji"),
+                Arg::Optional("count"),
+                Arg::Rest("text"),
+            ],
+            true,
+        );
+        assert_eq!(command.usage(), "[range]test <emoji> [count] <text...>");
+    }
+
+    #[test]
+    fn binds_arguments() {
+        let fixed = command(&[Arg::Required("a"), Arg::Optional("b")], false);
+        assert_eq!(fixed.bind(words(&["x"])).unwrap(), words(&["x"]));
+        assert_eq!(fixed.bind(words(&["x", "y"])).unwrap(), words(&["x", "y"]));
+        assert!(fixed.bind(words(&[])).is_err());
+        assert!(fixed.bind(words(&["x", "y", "z"])).is_err());
+
+        let rest = command(&[Arg::Required("a"), Arg::OptionalRest("b")], false);
+        assert_eq!(rest.bind(words(&["x"])).unwrap(), words(&["x"]));
+        assert_eq!(
+            rest.bind(words(&["x", "y", "z"])).unwrap(),
+            words(&["x", "y z"])
+        );
+    }
 }
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Duration, Utc};
+use parsing::cmd::{Endpoint, Range};
+use serenity::model::channel::Message;
+
+#[derive(Fail, Debug)]
+pub enum RangeError {
+    #[fail(display = "Only {} messages are loaded", _0)]
+    OutOfBounds(usize),
+    #[fail(display = "No loaded message matches /{}/", _0)]
+    NoMatch(String),
+    #[fail(display = "No loaded message is newer than {} minutes", _0)]
+    NoMoment(usize),
+    #[fail(display = "None of the loaded messages are yours")]
+    NoOwnMessage,
+    #[fail(display = "No messages of yours in range")]
+    NoOwnInRange,
+}
+
+/// What resolving a range needs to know about a message
+pub trait Entry {
+    fn sent_at(&self) -> DateTime<Utc>;
+    fn content(&self) -> &str;
+    fn is_own(&self) -> bool;
+}
+
+impl Entry for Message {
+    fn sent_at(&self) -> DateTime<Utc> {
+        self.timestamp.with_timezone(&Utc)
+    }
+
+    fn content(&self) -> &str {
+        &self.content
+    }
+
+    fn is_own(&self) -> bool {
+        Message::is_own(self)
+    }
+}
+
+/// Resolves a single endpoint to an index into `messages`, which are ordered oldest first
+fn resolve_endpoint<M: Entry>(endpoint: &Endpoint, messages: &[M]) -> Result<usize, RangeError> {
+    match *endpoint {
+        // `1` is the newest message, `2` the one before it, and so on
+        Endpoint::Fixed(back) => {
+            if back == 0 || back > messages.len() {
+                Err(RangeError::OutOfBounds(messages.len()))
+            } else {
+                Ok(messages.len() - back)
+            }
+        }
+        // The oldest message sent within the last `minutes` minutes
+        Endpoint::Moment(minutes) => {
+            let since = Utc::now() - Duration::minutes(minutes as i64);
+            messages
+                .iter()
+                .position(|msg| msg.sent_at() >= since)
+                .ok_or(RangeError::NoMoment(minutes))
+        }
+        // The most recent message containing the query
+        Endpoint::Search(query) => messages
+            .iter()
+            .rposition(|msg| msg.content().contains(query))
+            .ok_or_else(|| RangeError::NoMatch(query.to_owned())),
+    }
+}
+
+/// Resolves a range to an inclusive `(start, end)` pair of indices into `messages`
+///
+/// When no range is given the current user's most recent message is used
+pub fn resolve<M: Entry>(
+    range: &Option<Range>,
+    messages: &[M],
+) -> Result<(usize, usize), RangeError> {
+    match *range {
+        None => messages
+            .iter()
+            .rposition(|msg| msg.is_own())
+            .map(|i| (i, i))
+            .ok_or(RangeError::NoOwnMessage),
+        Some(Range::Single(ref endpoint)) => {
+            let i = resolve_endpoint(endpoint, messages)?;
+            Ok((i, i))
+        }
+        Some(Range::DoubledEnded(ref left, ref right)) => {
+            let left = resolve_endpoint(left, messages)?;
+            let right = resolve_endpoint(right, messages)?;
+            if left <= right {
+                Ok((left, right))
+            } else {
+                Ok((right, left))
+            }
+        }
+        Some(Range::PastToPresent(ref endpoint)) => {
+            let i = resolve_endpoint(endpoint, messages)?;
+            Ok((i, messages.len() - 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct TestMessage {
+        sent_at: DateTime<Utc>,
+        content: &'static str,
+        own: bool,
+    }
+
+    impl Entry for TestMessage {
+        fn sent_at(&self) -> DateTime<Utc> {
+            self.sent_at
+        }
+
+        fn content(&self) -> &str {
+            self.content
+        }
+
+        fn is_own(&self) -> bool {
+            self.own
+        }
+    }
+
+    /// Messages sent `minutes` ago, oldest first
+    fn messages(entries: &[(i64, &'static str, bool)]) -> Vec<TestMessage> {
+        entries
+            .iter()
+            .map(|&(minutes, content, own)| TestMessage {
+                sent_at: Utc::now() - Duration::minutes(minutes),
+                content,
+                own,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resolves_fixed_endpoints() {
+        let messages = messages(&[(30, "a", false), (20, "b", true), (10, "c", false)]);
+        let single = |back| Some(Range::Single(Endpoint::Fixed(back)));
+        assert_eq!(resolve(&single(1), &messages).unwrap(), (2, 2));
+        assert_eq!(resolve(&single(3), &messages).unwrap(), (0, 0));
+        match resolve(&single(4), &messages) {
+            Err(RangeError::OutOfBounds(3)) => {}
+            other => panic!("expected out of bounds, got {:?}", other),
+        }
+        match resolve(&single(0), &messages) {
+            Err(RangeError::OutOfBounds(3)) => {}
+            other => panic!("expected out of bounds, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolves_searches() {
+        let messages = messages(&[
+            (30, "hello", false),
+            (20, "hello again", true),
+            (10, "bye", false),
+        ]);
+        let search = |query| Some(Range::Single(Endpoint::Search(query)));
+        assert_eq!(resolve(&search("hello"), &messages).unwrap(), (1, 1));
+        match resolve(&search("missing"), &messages) {
+            Err(RangeError::NoMatch(ref query)) if query == "missing" => {}
+            other => panic!("expected no match, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn orders_double_ended_ranges() {
+        let messages = messages(&[(30, "a", false), (20, "b", true), (10, "c", false)]);
+        let range = Some(Range::DoubledEnded(Endpoint::Fixed(1), Endpoint::Fixed(3)));
+        assert_eq!(resolve(&range, &messages).unwrap(), (0, 2));
+    }
+
+    #[test]
+    fn resolves_past_to_present() {
+        let messages = messages(&[(30, "a", false), (20, "b", true), (10, "c", false)]);
+        let range = Some(Range::PastToPresent(Endpoint::Fixed(2)));
+        assert_eq!(resolve(&range, &messages).unwrap(), (1, 2));
+        let range = Some(Range::PastToPresent(Endpoint::Moment(25)));
+        assert_eq!(resolve(&range, &messages).unwrap(), (1, 2));
+    }
+
+    #[test]
+    fn defaults_to_own_message() {
+        let mine = messages(&[(30, "a", true), (20, "b", true), (10, "c", false)]);
+        assert_eq!(resolve(&None, &mine).unwrap(), (1, 1));
+
+        let others = messages(&[(30, "a", false), (10, "c", false)]);
+        match resolve(&None, &others) {
+            Err(RangeError::NoOwnMessage) => {}
+            other => panic!("expected no own message, got {:?}", other),
+        }
+    }
+}
@@ -31,6 +31,21 @@ impl EventHandler for Handler {
             .unwrap();
     }
 
+    fn reaction_add(&self, _: Context, reaction: Reaction) {
+        self.0.lock().send(ReactionAdd(reaction)).unwrap();
+    }
+
+    fn reaction_remove(&self, _: Context, reaction: Reaction) {
+        self.0.lock().send(ReactionRemove(reaction)).unwrap();
+    }
+
+    fn reaction_remove_all(&self, _: Context, channel: ChannelId, message: MessageId) {
+        self.0
+            .lock()
+            .send(ReactionRemoveAll(channel, message))
+            .unwrap();
+    }
+
     fn channel_update(&self, _: Context, _: Option<Channel>, _: Channel) {
         self.0.lock().send(ChannelUpdateEvent).unwrap();
     }
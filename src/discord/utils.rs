@@ -1,15 +1,10 @@
-use serenity::model::channel::{Channel, Message};
+use serde_json;
+use serenity::model::channel::{Channel, Group, Message, MessageReaction, ReactionType};
 use serenity::model::event::MessageUpdateEvent;
-use serenity::model::guild::{Guild, Member};
 use serenity::model::id::*;
-use serenity::prelude::RwLock;
 use serenity::CACHE;
 
-use std::sync::Arc;
-
-pub fn guild(message: &Message) -> Option<Arc<RwLock<Guild>>> {
-    guild_id(message).and_then(|guild_id| CACHE.read().guild(guild_id))
-}
+use helpers::emoji;
 
 pub fn guild_id(message: &Message) -> Option<GuildId> {
     match message.channel_id.get().ok() {
@@ -18,8 +13,119 @@ pub fn guild_id(message: &Message) -> Option<GuildId> {
     }
 }
 
-pub fn member(message: &Message) -> Option<Member> {
-    guild(message).and_then(|g| g.read().members.get(&message.author.id).cloned())
+/// Whether a message mentions a user, directly, through @everyone or through one of their roles
+pub fn mentions_user(message: &Message, user_id: UserId) -> bool {
+    if message.mention_everyone || message.mentions.iter().any(|user| user.id == user_id) {
+        return true;
+    }
+    if message.mention_roles.is_empty() {
+        return false;
+    }
+    guild_id(message)
+        .and_then(|guild_id| guild_id.find())
+        .and_then(|guild| guild.read().members.get(&user_id).cloned())
+        .map_or(false, |member| {
+            member
+                .roles
+                .iter()
+                .any(|role| message.mention_roles.contains(role))
+        })
+}
+
+/// Name of a group DM, falling back to its recipients' names
+pub fn group_name(group: &Group) -> String {
+    group.name.clone().unwrap_or_else(|| {
+        group
+            .recipients
+            .values()
+            .map(|user| user.read().name.clone())
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+/// Name of a channel as shown in messages, `#name` or `@recipient`
+pub fn channel_name(channel_id: ChannelId) -> String {
+    match channel_id.find() {
+        Some(Channel::Guild(channel)) => format!("#{}", channel.read().name),
+        Some(Channel::Category(category)) => format!("#{}", category.read().name),
+        Some(Channel::Private(channel)) => format!("@{}", channel.read().recipient.read().name),
+        Some(Channel::Group(group)) => format!("#{}", group_name(&group.read())),
+        None => "#unknown-channel".to_owned(),
+    }
+}
+
+/// Direct messages and group DMs in the cache along with their display names and last messages,
+/// most recently active first
+pub fn private_channels() -> Vec<(ChannelId, String, Option<MessageId>)> {
+    let cache = CACHE.read();
+    let mut channels = Vec::new();
+    for (id, channel) in &cache.private_channels {
+        let channel = channel.read();
+        let name = channel.recipient.read().name.clone();
+        channels.push((channel.last_message_id, *id, name));
+    }
+    for (id, group) in &cache.groups {
+        let group = group.read();
+        channels.push((group.last_message_id, *id, group_name(&group)));
+    }
+    channels.sort_by(|a, b| b.0.cmp(&a.0));
+    channels
+        .into_iter()
+        .map(|(last_message_id, id, name)| (id, name, last_message_id))
+        .collect()
+}
+
+/// Parses an emoji typed by the user: a unicode emoji, a `:shortcode:`, or a custom `<:name:id>`
+pub fn reaction_type(text: &str) -> ReactionType {
+    let trimmed = text.trim_matches(|c| c == '<' || c == '>');
+    let mut parts = trimmed.rsplitn(2, ':');
+    if let (Some(id), Some(name)) = (parts.next(), parts.next()) {
+        if let Ok(id) = id.parse() {
+            let animated = name.starts_with("a:");
+            let name = name.trim_left_matches("a:").trim_matches(':');
+            return ReactionType::Custom {
+                animated,
+                id: EmojiId(id),
+                name: Some(name.to_owned()),
+            };
+        }
+    }
+    match emoji::from_shortcode(text.trim_matches(':')) {
+        Some(emoji) => ReactionType::Unicode(emoji.to_owned()),
+        None => ReactionType::Unicode(text.to_owned()),
+    }
+}
+
+/// Counts a reaction on a message
+pub fn add_reaction(msg: &mut Message, reaction_type: &ReactionType, me: bool) {
+    if let Some(reaction) = msg
+        .reactions
+        .iter_mut()
+        .find(|reaction| reaction.reaction_type == *reaction_type)
+    {
+        reaction.count += 1;
+        reaction.me |= me;
+        return;
+    }
+    msg.reactions.push(MessageReaction {
+        count: 1,
+        me,
+        reaction_type: reaction_type.clone(),
+    });
+}
+
+/// Uncounts a reaction on a message, removing it once nobody has reacted with it
+pub fn remove_reaction(msg: &mut Message, reaction_type: &ReactionType, me: bool) {
+    for reaction in &mut msg.reactions {
+        if reaction.reaction_type == *reaction_type {
+            reaction.count = reaction.count.saturating_sub(1);
+            if me {
+                reaction.me = false;
+            }
+        }
+    }
+    msg.reactions.retain(|reaction| reaction.count > 0);
 }
 
 pub fn update_msg(msg: &mut Message, update: MessageUpdateEvent) {
@@ -59,7 +165,11 @@ pub fn update_msg(msg: &mut Message, update: MessageUpdateEvent) {
     if let Some(attachments) = update.attachments {
         msg.attachments = attachments;
     }
-    // if let Some(embeds) = update.embeds {
-    //     msg.embeds = embeds;
-    // }
+    if let Some(embeds) = update.embeds {
+        // Embeds arrive as raw JSON, any that fail to parse are dropped
+        msg.embeds = embeds
+            .into_iter()
+            .filter_map(|embed| serde_json::from_value(embed).ok())
+            .collect();
+    }
 }
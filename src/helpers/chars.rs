@@ -1,7 +1,10 @@
-#[derive(Debug, Clone)]
-enum CharSetType {
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CharSetType {
     Unicode,
     Nerd,
+    /// Plain ASCII, for fonts without emoji or box drawing characters
+    Ascii,
 }
 
 #[derive(Debug, Clone)]
@@ -11,7 +14,14 @@ pub struct CharSet {
 
 use self::CharSetType::*;
 
+/// Corners and edges of a box: top left, top right, bottom left, bottom right, horizontal, vertical
+pub type BoxChars = [char; 6];
+
 impl CharSet {
+    pub fn new(char_type: CharSetType) -> CharSet {
+        CharSet { char_type }
+    }
+
     pub fn unicode() -> CharSet {
         CharSet {
             char_type: CharSetType::Unicode,
@@ -28,6 +38,7 @@ impl CharSet {
         match self.char_type {
             Unicode => '\u{1F50A}',
             Nerd => '\u{f028}',
+            Ascii => '>',
         }
     }
 
@@ -35,6 +46,7 @@ impl CharSet {
         match self.char_type {
             Unicode => '\u{1F508}',
             Nerd => '\u{f026}',
+            Ascii => '>',
         }
     }
 
@@ -46,6 +58,46 @@ impl CharSet {
         match self.char_type {
             Unicode => '\u{1f4f7}', // 📷
             Nerd => '\u{f5ff}',
+            Ascii => '@',
+        }
+    }
+
+    /// Marks text cut short
+    pub fn ellipsis(&self) -> &'static str {
+        match self.char_type {
+            Unicode | Nerd => "…",
+            Ascii => "...",
+        }
+    }
+
+    /// Separates the guild, category and channel in channel labels
+    pub fn separator(&self) -> &'static str {
+        match self.char_type {
+            Unicode | Nerd => "›",
+            Ascii => ">",
+        }
+    }
+
+    /// Drawn to the left of block quotes
+    pub fn quote_bar(&self) -> &'static str {
+        match self.char_type {
+            Unicode | Nerd => "│ ",
+            Ascii => "| ",
+        }
+    }
+
+    /// Drawn to the left of embeds in the embed's colour
+    pub fn embed_bar(&self) -> &'static str {
+        match self.char_type {
+            Unicode | Nerd => "▌ ",
+            Ascii => "| ",
+        }
+    }
+
+    pub fn box_chars(&self) -> BoxChars {
+        match self.char_type {
+            Unicode | Nerd => ['┌', '┐', '└', '┘', '─', '│'],
+            Ascii => ['+', '+', '+', '+', '-', '|'],
         }
     }
 }
@@ -0,0 +1,401 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Number of kills remembered for yanking
+const KILL_RING_SIZE: usize = 16;
+
+/// Editable text with readline style motions and a kill ring
+///
+/// The text may span several lines, in which case line motions act on the line holding the cursor
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    text: String,
+    /// Byte offset of the cursor, always on a char boundary
+    cursor: usize,
+    /// Killed text, most recent last
+    kill_ring: Vec<String>,
+    /// Byte range of the last yank and the kill ring entry it came from, for cycling with yank_pop
+    last_yank: Option<(usize, usize, usize)>,
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+impl LineEditor {
+    pub fn new() -> LineEditor {
+        LineEditor::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Byte offset of the cursor
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces the text, leaving the cursor at the end
+    pub fn set_text(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+        self.last_yank = None;
+    }
+
+    /// Takes the text out of the editor, leaving it empty
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        self.last_yank = None;
+        ::std::mem::replace(&mut self.text, String::new())
+    }
+
+    pub fn clear(&mut self) {
+        self.take();
+    }
+
+    pub fn insert(&mut self, ch: char) {
+        self.text.insert(self.cursor, ch);
+        self.cursor += ch.len_utf8();
+        self.last_yank = None;
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+        self.last_yank = None;
+    }
+
+    /// Inserts a line break at the cursor
+    pub fn newline(&mut self) {
+        self.insert('\n');
+    }
+
+    /// Byte offset of the start of the line holding the cursor
+    fn line_start(&self) -> usize {
+        self.text[..self.cursor].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// Byte offset of the end of the line holding the cursor
+    fn line_end(&self) -> usize {
+        self.text[self.cursor..]
+            .find('\n')
+            .map_or(self.text.len(), |i| self.cursor + i)
+    }
+
+    pub fn lines(&self) -> Vec<&str> {
+        self.text.split('\n').collect()
+    }
+
+    /// Index of the line holding the cursor
+    pub fn cursor_line(&self) -> usize {
+        self.text[..self.cursor].matches('\n').count()
+    }
+
+    /// Replaces the text between `start` and the cursor
+    pub fn replace_before_cursor(&mut self, start: usize, s: &str) {
+        self.text.replace_range(start..self.cursor, s);
+        self.cursor = start + s.len();
+        self.last_yank = None;
+    }
+
+    fn prev_boundary(&self, index: usize) -> usize {
+        self.text[..index]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self, index: usize) -> usize {
+        self.text[index..]
+            .chars()
+            .next()
+            .map_or(index, |ch| index + ch.len_utf8())
+    }
+
+    /// Start of the word before `index`, skipping any separators in between
+    fn word_start(&self, index: usize) -> usize {
+        let before = &self.text[..index];
+        let word_end = before
+            .char_indices()
+            .rev()
+            .find(|&(_, ch)| is_word_char(ch))
+            .map_or(0, |(i, ch)| i + ch.len_utf8());
+        before[..word_end]
+            .char_indices()
+            .rev()
+            .find(|&(_, ch)| !is_word_char(ch))
+            .map_or(0, |(i, ch)| i + ch.len_utf8())
+    }
+
+    /// End of the word after `index`, skipping any separators in between
+    fn word_end(&self, index: usize) -> usize {
+        let after = &self.text[index..];
+        let word_start = after
+            .char_indices()
+            .find(|&(_, ch)| is_word_char(ch))
+            .map_or(after.len(), |(i, _)| i);
+        index + after[word_start..]
+            .char_indices()
+            .find(|&(_, ch)| !is_word_char(ch))
+            .map_or(after.len(), |(i, _)| word_start + i)
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            let start = self.prev_boundary(self.cursor);
+            self.text.replace_range(start..self.cursor, "");
+            self.cursor = start;
+        }
+        self.last_yank = None;
+    }
+
+    pub fn delete(&mut self) {
+        let end = self.next_boundary(self.cursor);
+        self.text.replace_range(self.cursor..end, "");
+        self.last_yank = None;
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.prev_boundary(self.cursor);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = self.next_boundary(self.cursor);
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = self.line_start();
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.line_end();
+    }
+
+    /// Moves the cursor to the same column of the previous line, returning false on the first line
+    pub fn line_up(&mut self) -> bool {
+        let start = self.line_start();
+        if start == 0 {
+            return false;
+        }
+        let column = self.cursor_column();
+        self.cursor = start - 1;
+        self.cursor = self.line_start();
+        self.move_to_column(column);
+        true
+    }
+
+    /// Moves the cursor to the same column of the next line, returning false on the last line
+    pub fn line_down(&mut self) -> bool {
+        let end = self.line_end();
+        if end == self.text.len() {
+            return false;
+        }
+        let column = self.cursor_column();
+        self.cursor = end + 1;
+        self.move_to_column(column);
+        true
+    }
+
+    /// Moves the cursor from the start of its line to `column`, or the end of the line if shorter
+    fn move_to_column(&mut self, column: usize) {
+        let end = self.line_end();
+        while self.cursor < end && self.cursor_column() < column {
+            self.right();
+        }
+    }
+
+    pub fn word_left(&mut self) {
+        self.cursor = self.word_start(self.cursor);
+    }
+
+    pub fn word_right(&mut self) {
+        self.cursor = self.word_end(self.cursor);
+    }
+
+    /// Removes a range of text, pushing it onto the kill ring
+    fn kill(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        let killed: String = self.text.drain(start..end).collect();
+        if self.kill_ring.len() == KILL_RING_SIZE {
+            self.kill_ring.remove(0);
+        }
+        self.kill_ring.push(killed);
+        self.cursor = start;
+        self.last_yank = None;
+    }
+
+    /// Kills the word before the cursor (Ctrl-W)
+    pub fn kill_word_back(&mut self) {
+        let start = self.word_start(self.cursor);
+        self.kill(start, self.cursor);
+    }
+
+    /// Kills from the cursor to the end of the line (Ctrl-K)
+    pub fn kill_to_end(&mut self) {
+        let end = self.line_end();
+        self.kill(self.cursor, end);
+    }
+
+    /// Kills from the start of the line to the cursor (Ctrl-U)
+    pub fn kill_to_start(&mut self) {
+        let start = self.line_start();
+        self.kill(start, self.cursor);
+    }
+
+    /// Inserts the most recent kill (Ctrl-Y)
+    pub fn yank(&mut self) {
+        if let Some(killed) = self.kill_ring.last().cloned() {
+            let start = self.cursor;
+            self.insert_str(&killed);
+            self.last_yank = Some((start, self.cursor, self.kill_ring.len() - 1));
+        }
+    }
+
+    /// Replaces the text just yanked with the previous kill (Alt-Y)
+    pub fn yank_pop(&mut self) {
+        if let Some((start, end, index)) = self.last_yank {
+            let index = if index == 0 {
+                self.kill_ring.len() - 1
+            } else {
+                index - 1
+            };
+            let killed = self.kill_ring[index].clone();
+            self.text.replace_range(start..end, &killed);
+            self.cursor = start + killed.len();
+            self.last_yank = Some((start, self.cursor, index));
+        }
+    }
+
+    /// Display column of the cursor within its line
+    pub fn cursor_column(&self) -> usize {
+        self.text[self.line_start()..self.cursor].width()
+    }
+
+    /// Adjusts a horizontal scroll offset so the cursor stays within `width` columns
+    pub fn scroll_offset(&self, offset: usize, width: usize) -> usize {
+        let column = self.cursor_column();
+        if column < offset {
+            column
+        } else if column >= offset + width {
+            column + 1 - width
+        } else {
+            offset
+        }
+    }
+}
+
+/// The part of a line visible when scrolled `offset` columns, clipped to `width` columns
+pub fn visible(line: &str, offset: usize, width: usize) -> String {
+    let mut column = 0;
+    let mut visible = String::new();
+    for ch in line.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        if column >= offset {
+            if column + ch_width > offset + width {
+                break;
+            }
+            visible.push(ch);
+        }
+        column += ch_width;
+    }
+    visible
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::new();
+        editor.set_text(text.to_owned());
+        editor
+    }
+
+    #[test]
+    fn insert_mid_text() {
+        let mut editor = editor("hllo");
+        editor.home();
+        editor.right();
+        editor.insert('e');
+
+        assert_eq!(editor.text(), "hello");
+        assert_eq!(editor.cursor(), 2);
+    }
+
+    #[test]
+    fn unicode_cursor() {
+        let mut editor = editor("añ日");
+        editor.left();
+        assert_eq!(editor.cursor_column(), 2);
+        editor.backspace();
+
+        assert_eq!(editor.text(), "a日");
+        assert_eq!(editor.cursor_column(), 1);
+    }
+
+    #[test]
+    fn word_motion() {
+        let mut editor = editor("foo  bar.baz");
+        editor.word_left();
+        assert_eq!(editor.cursor(), 9);
+        editor.word_left();
+        assert_eq!(editor.cursor(), 5);
+        editor.word_right();
+        assert_eq!(editor.cursor(), 8);
+    }
+
+    #[test]
+    fn kill_and_yank() {
+        let mut editor = editor("one two three");
+        editor.kill_word_back();
+        editor.kill_word_back();
+        assert_eq!(editor.text(), "one ");
+
+        editor.yank();
+        assert_eq!(editor.text(), "one two ");
+        editor.yank_pop();
+        assert_eq!(editor.text(), "one three");
+    }
+
+    #[test]
+    fn line_motion() {
+        let mut editor = editor("one\ntwo three");
+        editor.home();
+        assert_eq!(editor.cursor(), 4);
+        assert_eq!(editor.cursor_line(), 1);
+
+        editor.kill_to_end();
+        assert_eq!(editor.text(), "one\n");
+        editor.left();
+        editor.kill_to_start();
+        assert_eq!(editor.lines(), vec!["", ""]);
+    }
+
+    #[test]
+    fn horizontal_scroll() {
+        let editor = editor("abcdefgh");
+        let offset = editor.scroll_offset(0, 4);
+
+        assert_eq!(offset, 5);
+        assert_eq!(visible(editor.text(), offset, 4), "fgh");
+    }
+
+    #[test]
+    fn vertical_motion() {
+        let mut editor = editor("abc\nd\nefg");
+        assert!(editor.line_up());
+        assert_eq!(editor.cursor(), 5);
+        assert!(editor.line_up());
+        assert_eq!(editor.cursor(), 1);
+        assert!(!editor.line_up());
+        assert!(editor.line_down());
+        assert!(editor.line_down());
+        assert!(!editor.line_down());
+    }
+}
@@ -0,0 +1,73 @@
+/// Common emoji shortcodes, without their surrounding colons
+pub const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("angry", "😠"),
+    ("blush", "😊"),
+    ("broken_heart", "💔"),
+    ("clap", "👏"),
+    ("confused", "😕"),
+    ("cry", "😢"),
+    ("eyes", "👀"),
+    ("facepalm", "🤦"),
+    ("fire", "🔥"),
+    ("flushed", "😳"),
+    ("grimacing", "😬"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("hugging", "🤗"),
+    ("innocent", "😇"),
+    ("joy", "😂"),
+    ("kissing_heart", "😘"),
+    ("laughing", "😆"),
+    ("neutral_face", "😐"),
+    ("ok_hand", "👌"),
+    ("party", "🎉"),
+    ("pensive", "😔"),
+    ("pray", "🙏"),
+    ("rage", "😡"),
+    ("raised_hands", "🙌"),
+    ("relaxed", "☺️"),
+    ("relieved", "😌"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("scream", "😱"),
+    ("shrug", "🤷"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("slight_smile", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("sob", "😭"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("stuck_out_tongue", "😛"),
+    ("sunglasses", "😎"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tired_face", "😫"),
+    ("unamused", "😒"),
+    ("upside_down", "🙃"),
+    ("wave", "👋"),
+    ("weary", "😩"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("x", "❌"),
+    ("yum", "😋"),
+    ("zipper_mouth", "🤐"),
+];
+
+/// Looks up the emoji for a shortcode, without its surrounding colons
+pub fn from_shortcode(shortcode: &str) -> Option<&'static str> {
+    SHORTCODES
+        .iter()
+        .find(|(name, _)| *name == shortcode)
+        .map(|(_, emoji)| *emoji)
+}
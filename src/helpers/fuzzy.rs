@@ -0,0 +1,54 @@
+/// Scores how well `query` matches `candidate` as a case-insensitive subsequence
+///
+/// Returns `None` when the query does not match, otherwise higher scores are better matches.
+/// Consecutive matches and matches at the start of a word are preferred.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut last_match: Option<usize> = None;
+
+    for query_ch in query.chars().flat_map(char::to_lowercase) {
+        let found = candidate[position..]
+            .iter()
+            .position(|ch| ch.to_lowercase().eq(Some(query_ch)))?;
+        let index = position + found;
+
+        score += 1;
+        match last_match {
+            Some(last) if last + 1 == index => score += 5,
+            Some(last) => score -= (index - last) as i64 / 4,
+            None => score -= index as i64 / 4,
+        }
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        last_match = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod test {
+    use super::score;
+
+    #[test]
+    fn empty_query() {
+        assert_eq!(score("", "general"), Some(0));
+    }
+
+    #[test]
+    fn subsequence() {
+        assert!(score("gnrl", "general").is_some());
+        assert!(score("GEN", "general").is_some());
+        assert!(score("lareneg", "general").is_none());
+    }
+
+    #[test]
+    fn prefers_consecutive_and_word_starts() {
+        assert!(score("gen", "general") > score("gen", "gaming-en"));
+        assert!(score("dev", "rust › #dev") > score("dev", "rust › #adverts"));
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+
+/// A small map that forgets its least recently stored entry once full
+pub struct Lru<K, V> {
+    capacity: usize,
+    /// Most recently stored first
+    entries: VecDeque<(K, V)>,
+}
+
+impl<K: PartialEq, V> Lru<K, V> {
+    pub fn new(capacity: usize) -> Lru<K, V> {
+        Lru {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Stores an entry as the most recent, evicting the oldest if full
+    pub fn put(&mut self, key: K, value: V) {
+        self.take(&key);
+        self.entries.push_front((key, value));
+        self.entries.truncate(self.capacity);
+    }
+
+    /// Removes and returns an entry
+    pub fn take(&mut self, key: &K) -> Option<V> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        self.entries.remove(index).map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.iter_mut().map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Lru;
+
+    #[test]
+    fn evicts_oldest() {
+        let mut lru = Lru::new(2);
+        lru.put(1, "a");
+        lru.put(2, "b");
+        lru.put(3, "c");
+
+        assert_eq!(lru.take(&1), None);
+        assert_eq!(lru.take(&2), Some("b"));
+        assert_eq!(lru.take(&2), None);
+    }
+
+    #[test]
+    fn put_refreshes() {
+        let mut lru = Lru::new(2);
+        lru.put(1, "a");
+        lru.put(2, "b");
+        lru.put(1, "c");
+        lru.put(3, "d");
+
+        assert_eq!(lru.get_mut(&2), None);
+        assert_eq!(lru.get_mut(&1), Some(&mut "c"));
+    }
+}
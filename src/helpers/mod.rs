@@ -1,2 +1,9 @@
 pub mod chars;
+pub mod editor;
+pub mod emoji;
+pub mod fuzzy;
+pub mod lru;
+pub mod notify;
 pub mod signal;
+pub mod size;
+pub mod system;
@@ -0,0 +1,160 @@
+use failure::Error;
+use notify_rust;
+use serde_json;
+
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// A way of delivering notifications
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Desktop notifications through notify-rust
+    Desktop,
+    /// The terminal bell
+    Bell,
+    /// OSC 9, understood by iTerm2, kitty and others
+    Osc9,
+    /// OSC 777, understood by urxvt and VTE based terminals
+    Osc777,
+    /// The external command configured in `notifications.command`
+    Command,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Notification<'a> {
+    pub sender: &'a str,
+    pub channel: &'a str,
+    pub body: &'a str,
+}
+
+impl<'a> Notification<'a> {
+    fn title(&self) -> String {
+        format!("{} in {}", self.sender, self.channel)
+    }
+}
+
+/// Strips characters that would end or corrupt an escape sequence
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+fn osc9(notification: &Notification) -> String {
+    format!(
+        "\x1b]9;{}: {}\x07",
+        sanitize(&notification.title()),
+        sanitize(notification.body)
+    )
+}
+
+fn osc777(notification: &Notification) -> String {
+    format!(
+        "\x1b]777;notify;{};{}\x07",
+        sanitize(&notification.title()).replace(';', ","),
+        sanitize(notification.body)
+    )
+}
+
+/// Wraps an escape sequence so tmux passes it on to the outer terminal
+fn tmux_passthrough(sequence: &str) -> String {
+    format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+}
+
+fn write_escape(sequence: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    if env::var_os("TMUX").is_some() {
+        write!(stdout, "{}", tmux_passthrough(sequence))?;
+    } else {
+        write!(stdout, "{}", sequence)?;
+    }
+    stdout.flush()
+}
+
+/// Runs `command` with the sender, channel and body as trailing arguments, or as JSON on stdin
+fn run_command(command: &[String], json: bool, notification: &Notification) -> Result<(), Error> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| format_err!("No notification command configured"))?;
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if json {
+        command.stdin(Stdio::piped());
+    } else {
+        command.stdin(Stdio::null()).args(&[
+            notification.sender,
+            notification.channel,
+            notification.body,
+        ]);
+    }
+
+    let mut child = command.spawn()?;
+    if json {
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(serde_json::to_string(notification)?.as_bytes())?;
+        }
+    }
+    // Reap the command without blocking the event loop
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Delivers a notification through one backend
+pub fn send(
+    backend: Backend,
+    notification: &Notification,
+    command: &[String],
+    json: bool,
+) -> Result<(), Error> {
+    match backend {
+        Backend::Desktop => {
+            notify_rust::Notification::new()
+                .summary(&notification.title())
+                .body(notification.body)
+                .show()
+                .map_err(|err| format_err!("{}", err))?;
+        }
+        Backend::Bell => write_escape("\x07")?,
+        Backend::Osc9 => write_escape(&osc9(notification))?,
+        Backend::Osc777 => write_escape(&osc777(notification))?,
+        Backend::Command => run_command(command, json, notification)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOTIFICATION: Notification = Notification {
+        sender: "jackson",
+        channel: "#general",
+        body: "hi;\nthere",
+    };
+
+    #[test]
+    fn formats_escapes() {
+        assert_eq!(
+            osc9(&NOTIFICATION),
+            "\x1b]9;jackson in #general: hi; there\x07"
+        );
+        assert_eq!(
+            osc777(&NOTIFICATION),
+            "\x1b]777;notify;jackson in #general;hi; there\x07"
+        );
+    }
+
+    #[test]
+    fn wraps_for_tmux() {
+        assert_eq!(
+            tmux_passthrough("\x1b]9;x\x07"),
+            "\x1bPtmux;\x1b\x1b]9;x\x07\x1b\\"
+        );
+    }
+}
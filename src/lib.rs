@@ -4,6 +4,7 @@
 extern crate failure;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate chrono;
 extern crate serenity;
 extern crate termbuf;
@@ -14,6 +15,7 @@ extern crate notify_rust;
 extern crate parsing;
 extern crate signal;
 extern crate textwrap;
+extern crate unicode_width;
 
 mod command;
 mod discord;
@@ -1,16 +1,21 @@
+use chrono::Local;
 use failure::Error;
-use notify_rust::Notification;
+use serenity::model::channel::{Channel, Message};
 use serenity::prelude::RwLock;
 use serenity::CACHE;
+use termbuf::termion::event::Key;
 
 use std::sync::mpsc::{self, Receiver};
 use std::sync::Arc;
+use std::thread;
 
 use command::CommandHandler;
+use discord::utils;
 use discord::DiscordClient;
+use helpers::notify::{self, Notification};
 use helpers::signal::SignalHandler;
 use model::State as SavedState;
-use model::{Context, Event, MessageItem, Preferences};
+use model::{Action, Context, Event, History, Keymap, MessageItem, Preferences, Theme};
 use view::View;
 
 enum State {
@@ -32,6 +37,12 @@ impl Application {
     pub fn new() -> Result<Application, Error> {
         let preferences = Preferences::load()?;
         let state = SavedState::load()?;
+        let history = History::load()?;
+        let keymap = Keymap::from_config(&preferences.keys)?;
+        let theme = match preferences.theme {
+            Some(ref name) => Theme::load(name)?,
+            None => Theme::default(),
+        };
 
         let state = state;
 
@@ -42,10 +53,12 @@ impl Application {
         let context = Arc::new(RwLock::new(Context::new(
             &preferences,
             &state,
+            keymap,
+            theme,
             event_channel,
         )));
 
-        let view = View::new(&context.clone());
+        let view = View::new(&context.clone(), history);
 
         let command_handler = CommandHandler::new();
 
@@ -63,6 +76,7 @@ impl Application {
 
     pub fn run(&mut self) -> Result<(), Error> {
         self.view.message_view.load_messages(self);
+        self.mark_current_read();
 
         loop {
             match self.state {
@@ -76,6 +90,7 @@ impl Application {
                     debug!("Exiting event loop");
                     trace!("Saving state...");
                     self.context.read().save_state()?;
+                    self.view.input_view.save_history()?;
                     debug!("Saved state");
                     break;
                 }
@@ -86,13 +101,15 @@ impl Application {
     }
 
     pub fn wait_for_event(&mut self) {
-        use termbuf::termion::event::Key;
         let event = self.events.recv();
         trace!("Event: {:?}", event);
         match event {
             Ok(Event::InternalError(err)) => {
                 error!("Internal error: {}", err);
             }
+            Ok(Event::Notice(text)) => {
+                self.view.message_view.add_msg(MessageItem::Notice(text));
+            }
             Ok(Event::DiscordReady) => {
                 debug!("Discord ready");
                 self.context.write().current_user = Some(CACHE.read().user.clone());
@@ -100,36 +117,36 @@ impl Application {
 
                 self.view.guild_list.populate_guild_list();
             }
-            Ok(Event::Keypress(key)) => match key {
-                Key::Ctrl('c') | Key::Ctrl('d') => {
-                    self.discord_client.shutdown();
-                    self.state = State::Exiting;
-                }
-                key => {
-                    if let Err(err) = self.view.input_view.key_press(key) {
-                        self.send_err(format_err!("Error handling input: {}", err))
-                    }
-                }
-            },
+            Ok(Event::Keypress(key)) => self.handle_key(key),
             Ok(Event::ShutdownAll) => {
                 self.discord_client.shutdown();
                 self.state = State::Exiting;
             }
             Ok(Event::NewMessage(msg)) => {
-                if Some(msg.channel_id) == self.context.read().channel {
-                    if !msg.is_own() {
-                        if let Err(e) = Notification::new()
-                            .summary(&msg.author.name)
-                            .body(&msg.content)
-                            .show()
-                        {
-                            self.send_err(format_err!("Error displaying notification: {}", e));
-                        }
+                let notify = {
+                    let mut context = self.context.write();
+                    if Some(msg.channel_id) == context.channel || msg.is_own() {
+                        context.unread.mark_read(msg.channel_id, msg.id);
+                        false
+                    } else {
+                        let mentioned = context
+                            .current_user
+                            .as_ref()
+                            .map_or(false, |user| utils::mentions_user(&msg, user.id));
+                        context.unread.add_message(msg.channel_id, msg.id, mentioned);
+                        context.notifications.should_notify(
+                            utils::guild_id(&msg),
+                            msg.channel_id,
+                            mentioned,
+                            &msg.content,
+                            Local::now().time(),
+                        )
                     }
-                    self.view
-                        .message_view
-                        .add_msg(MessageItem::DiscordMessage(msg));
+                };
+                if notify {
+                    self.notify(&msg);
                 }
+                self.view.message_view.add_message(msg);
             }
             Ok(Event::MessageDelete(channel_id, message_id)) => {
                 self.view.message_view.delete_msg(channel_id, message_id)
@@ -139,6 +156,19 @@ impl Application {
                 .message_view
                 .delete_msg_bulk(channel_id, &message_ids),
             Ok(Event::MessageUpdateEvent(update)) => self.view.message_view.update_message(*update),
+            Ok(Event::ReactionAdd(reaction)) => self.view.message_view.add_reaction(&reaction),
+            Ok(Event::ReactionRemove(reaction)) => self.view.message_view.remove_reaction(&reaction),
+            Ok(Event::ReactionRemoveAll(channel_id, message_id)) => self
+                .view
+                .message_view
+                .remove_all_reactions(channel_id, message_id),
+            Ok(Event::HistoryLoaded(channel_id, messages)) => {
+                self.view.message_view.add_history(channel_id, messages)
+            }
+            Ok(Event::HistoryFailed(channel_id)) => {
+                self.view.message_view.history_failed(channel_id)
+            }
+            Ok(Event::Scroll(scroll)) => self.view.message_view.scroll(scroll),
             Ok(Event::ChannelUpdateEvent) => self.view.guild_list.populate_guild_list(),
             Ok(Event::UserMessage(msg)) => {
                 if self
@@ -152,10 +182,50 @@ impl Application {
                 }
             }
             Ok(Event::SetChannel(new_chan)) => {
-                self.context.write().channel = Some(new_chan);
+                {
+                    let mut context = self.context.write();
+                    context.channel = Some(new_chan);
+                    context.guild = match new_chan.find() {
+                        Some(Channel::Guild(channel)) => Some(channel.read().guild_id),
+                        _ => None,
+                    };
+                }
+                self.view.input_view.set_channel(Some(new_chan));
                 self.view.message_view.load_messages(self);
+                self.mark_current_read();
+            }
+            Ok(Event::FocusSidebar) => {
+                self.context.write().guild_sidebar_visible = true;
+                let current_channel = self.context.read().channel;
+                self.view.guild_list.focus(current_channel);
+            }
+            Ok(Event::SelectMessages) => self.view.message_view.select_last(),
+            Ok(Event::Reply(msg)) => {
+                let nick = self.view.message_view.display_name(&msg);
+                self.view
+                    .input_view
+                    .reply(&msg.content, &nick, msg.author.id);
+            }
+            Ok(Event::StartEdit(msg)) => {
+                self.view
+                    .input_view
+                    .edit(msg.channel_id, msg.id, msg.content.clone());
+            }
+            Ok(Event::EditMessage(channel_id, message_id, content)) => {
+                let event_channel = self.context.read().event_channel.clone();
+                thread::spawn(move || {
+                    if let Err(err) = channel_id.edit_message(message_id, |m| m.content(&content)) {
+                        event_channel
+                            .send(Event::Notice(format!("Unable to edit message: {}", err)))
+                            .unwrap();
+                    }
+                });
+            }
+            Ok(Event::UserCommand(cmd)) => {
+                self.command_handler.execute(self, &cmd);
+                self.view.message_view.clear_selection();
             }
-            Ok(Event::UserCommand(cmd)) => self.command_handler.execute(self, &cmd),
+            Ok(Event::CommandCancelled) => self.view.message_view.clear_selection(),
             Ok(Event::UserTyping) => {
                 if let Some(channel) = self.context.read().channel {
                     if let Err(err) = channel.broadcast_typing() {
@@ -173,6 +243,108 @@ impl Application {
         }
     }
 
+    fn handle_key(&mut self, key: Key) {
+        let action = self.context.read().keymap.action(key);
+        // The switcher, sidebar and selection mode have their own keys
+        let modal = self.view.switcher.is_open()
+            || self.view.guild_list.focused()
+            || self.view.message_view.selecting();
+        match action {
+            Some(Action::Quit) => {
+                self.discord_client.shutdown();
+                self.state = State::Exiting;
+            }
+            // While composing a message the input kills to the end of the line instead
+            Some(Action::SwitchChannel)
+                if !self.view.switcher.is_open() && self.view.input_view.is_empty() =>
+            {
+                let mut entries = self.view.guild_list.channel_labels();
+                entries.extend(
+                    utils::private_channels()
+                        .into_iter()
+                        .map(|(id, name, _)| (format!("@{}", name), id)),
+                );
+                self.view.switcher.open(entries);
+            }
+            Some(Action::Complete) if !modal => {
+                let guild = self.context.read().guild;
+                if !self.view.input_view.complete(guild, &self.command_handler) {
+                    if let Err(err) = self.view.input_view.key_press(key) {
+                        self.send_err(format_err!("Error handling input: {}", err))
+                    }
+                }
+            }
+            Some(Action::ToggleSidebar) if !modal => self.toggle_sidebar(),
+            Some(Action::NextChannel) | Some(Action::PrevChannel) if !modal => {
+                let current = self.context.read().channel;
+                let forward = action == Some(Action::NextChannel);
+                if let Some(channel) = self.view.guild_list.adjacent_channel(current, forward) {
+                    self.context
+                        .read()
+                        .event_channel
+                        .send(Event::SetChannel(channel))
+                        .unwrap();
+                }
+            }
+            _ => {
+                let result = if self.view.switcher.is_open() {
+                    self.view.switcher.key_press(key)
+                } else if self.view.guild_list.focused() {
+                    self.view.guild_list.key_press(key)
+                } else if self.view.message_view.selecting() {
+                    self.view.message_view.key_press(key)
+                } else {
+                    self.view.input_view.key_press(key)
+                };
+                if let Err(err) = result {
+                    self.send_err(format_err!("Error handling input: {}", err))
+                }
+            }
+        }
+    }
+
+    /// Shows or hides the guild sidebar
+    pub fn toggle_sidebar(&self) {
+        let mut context = self.context.write();
+        context.guild_sidebar_visible = !context.guild_sidebar_visible;
+    }
+
+    /// Delivers a notification about a message through every configured backend
+    fn notify(&self, msg: &Message) {
+        let channel = utils::channel_name(msg.channel_id);
+        let notification = Notification {
+            sender: &msg.author.name,
+            channel: &channel,
+            body: &msg.content,
+        };
+        let errors = {
+            let context = self.context.read();
+            let settings = &context.notifications;
+            settings
+                .backends
+                .iter()
+                .filter_map(|backend| {
+                    notify::send(
+                        *backend,
+                        &notification,
+                        &settings.command,
+                        settings.command_json,
+                    ).err()
+                })
+                .collect::<Vec<_>>()
+        };
+        for err in errors {
+            self.send_err(format_err!("Error displaying notification: {}", err));
+        }
+    }
+
+    /// Marks everything loaded in the current channel as read
+    fn mark_current_read(&self) {
+        if let Some((channel_id, message_id)) = self.view.message_view.last_message() {
+            self.context.write().unread.mark_read(channel_id, message_id);
+        }
+    }
+
     fn send_err(&self, err: Error) {
         self.context
             .read()
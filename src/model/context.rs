@@ -1,10 +1,11 @@
+use std::path::PathBuf;
 use std::sync::mpsc::Sender;
 
 use serenity::model::id::{ChannelId, GuildId};
 use serenity::model::user::CurrentUser;
 
 use helpers::chars::CharSet;
-use model::{Event, Preferences, State};
+use model::{Event, Keymap, Notifications, Preferences, State, Theme, Unread};
 
 use failure::Error;
 
@@ -15,6 +16,16 @@ pub struct Context {
     pub timestamp_fmt: String,
     /// Whether or not to use Nerd Fonts
     pub nerd_fonts: bool,
+    /// Maximum height of the input box
+    pub input_rows: usize,
+    /// Width of the guild sidebar
+    pub sidebar_width: usize,
+    /// Where downloaded attachments are saved
+    pub download_dir: PathBuf,
+    /// When new messages notify
+    pub notifications: Notifications,
+    /// Actions bound to keys
+    pub keymap: Keymap,
 
     /// Whether or not to show the guild sidebar
     pub guild_sidebar_visible: bool,
@@ -32,10 +43,21 @@ pub struct Context {
 
     /// Charset to use throughout the app
     pub char_set: CharSet,
+    /// Colours and styles to use throughout the app
+    pub theme: Theme,
+
+    /// Unread messages and mentions per channel
+    pub unread: Unread,
 }
 
 impl Context {
-    pub fn new(prefs: &Preferences, state: &State, event_channel: Sender<Event>) -> Context {
+    pub fn new(
+        prefs: &Preferences,
+        state: &State,
+        keymap: Keymap,
+        theme: Theme,
+        event_channel: Sender<Event>,
+    ) -> Context {
         // Prefs
         let token = prefs.token.clone();
 
@@ -46,10 +68,18 @@ impl Context {
 
         let nerd_fonts = prefs.nerd_fonts.unwrap_or(false);
 
-        let char_set = if nerd_fonts {
-            CharSet::nerd()
-        } else {
-            CharSet::unicode()
+        let input_rows = prefs.input_rows();
+
+        let sidebar_width = prefs.sidebar_width();
+
+        let download_dir = prefs.download_dir();
+
+        let notifications = prefs.notifications.clone();
+
+        let char_set = match prefs.charset {
+            Some(char_type) => CharSet::new(char_type),
+            None if nerd_fonts => CharSet::nerd(),
+            None => CharSet::unicode(),
         };
 
         // State
@@ -60,16 +90,25 @@ impl Context {
 
         let current_user = None;
 
+        let unread = Unread::from_saved(&state.last_read);
+
         Context {
             token,
             timestamp_fmt,
             nerd_fonts,
+            input_rows,
+            sidebar_width,
+            download_dir,
+            notifications,
+            keymap,
             guild_sidebar_visible,
             event_channel,
             channel,
             guild,
             current_user,
             char_set,
+            theme,
+            unread,
         }
     }
 
@@ -82,6 +121,7 @@ impl Context {
             channel: self.channel,
             guild: self.guild,
             guild_sidebar_visible: self.guild_sidebar_visible,
+            last_read: self.unread.to_saved(),
         }
     }
 }
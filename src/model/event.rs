@@ -5,6 +5,14 @@ use termbuf::termion::event::Key;
 
 use failure::Error;
 
+#[derive(Debug, Clone, Copy)]
+pub enum Scroll {
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+}
+
 #[derive(Debug)]
 pub enum Event {
     ShutdownAll,
@@ -12,14 +20,32 @@ pub enum Event {
     MessageDelete(ChannelId, MessageId),
     MessageDeleteBulk(ChannelId, Vec<MessageId>),
     MessageUpdateEvent(Box<MessageUpdateEvent>),
+    ReactionAdd(channel::Reaction),
+    ReactionRemove(channel::Reaction),
+    ReactionRemoveAll(ChannelId, MessageId),
+    HistoryLoaded(ChannelId, Vec<channel::Message>),
+    /// Fetching older messages for a channel failed, so it may be retried
+    HistoryFailed(ChannelId),
     ChannelUpdateEvent,
     DiscordReady,
     SetChannel(ChannelId),
+    FocusSidebar,
+    /// Start selecting messages in the message view
+    SelectMessages,
+    /// Start composing a reply to a message
+    Reply(Box<channel::Message>),
+    /// Load one of my messages into the input for editing
+    StartEdit(Box<channel::Message>),
+    EditMessage(ChannelId, MessageId, String),
     Keypress(Key),
+    Scroll(Scroll),
     UserMessage(String),
     UserCommand(String),
+    /// Command mode was left without running the command
+    CommandCancelled,
     UserTyping,
     TypingStart(TypingStartEvent),
     InternalError(Error),
+    Notice(String),
     WindowSizeChange,
 }
@@ -0,0 +1,108 @@
+use failure::Error;
+use toml;
+
+use serenity::model::id::ChannelId;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+
+use error::*;
+
+/// Entries kept in each history ring
+const MAX_ENTRIES: usize = 100;
+
+/// Previously sent messages and commands, oldest first
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct History {
+    #[serde(default)]
+    commands: Vec<String>,
+    /// Sent messages per channel, keyed by channel id since TOML keys must be strings
+    #[serde(default)]
+    messages: HashMap<String, Vec<String>>,
+}
+
+fn push(ring: &mut Vec<String>, entry: String) {
+    if entry.trim().is_empty() || ring.last() == Some(&entry) {
+        return;
+    }
+    if ring.len() == MAX_ENTRIES {
+        ring.remove(0);
+    }
+    ring.push(entry);
+}
+
+impl History {
+    pub fn load() -> Result<History, Error> {
+        let home_dir = env::home_dir().ok_or(HomeDirError)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(home_dir.join(".config/dex/history.toml"))?;
+        let mut buf = String::new();
+        file.read_to_string(&mut buf)?;
+
+        let history: History = toml::from_str(&buf)?;
+        Ok(history)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let home_dir = env::home_dir().ok_or(HomeDirError)?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(home_dir.join(".config/dex/history.toml"))?;
+
+        let data = toml::to_string(self)?;
+        file.write_all(data.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    pub fn messages(&self, channel: ChannelId) -> &[String] {
+        self.messages
+            .get(&channel.0.to_string())
+            .map_or(&[], |messages| messages.as_slice())
+    }
+
+    pub fn push_command(&mut self, command: String) {
+        push(&mut self.commands, command);
+    }
+
+    pub fn push_message(&mut self, channel: ChannelId, message: String) {
+        push(
+            self.messages.entry(channel.0.to_string()).or_default(),
+            message,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clean_history() {
+        let history: History = toml::from_str("").unwrap();
+
+        assert!(history.commands().is_empty());
+        assert!(history.messages(ChannelId(1)).is_empty());
+    }
+
+    #[test]
+    fn skips_repeats() {
+        let mut history = History::default();
+        history.push_message(ChannelId(1), "hi".to_owned());
+        history.push_message(ChannelId(1), "hi".to_owned());
+        history.push_message(ChannelId(2), "hi".to_owned());
+
+        assert_eq!(history.messages(ChannelId(1)), ["hi".to_owned()]);
+        assert_eq!(history.messages(ChannelId(2)), ["hi".to_owned()]);
+    }
+}
@@ -0,0 +1,287 @@
+use termbuf::termion::event::Key;
+
+use std::collections::HashMap;
+
+#[derive(Fail, Debug)]
+pub enum KeymapError {
+    #[fail(display = "Unknown key \"{}\" in [keys]", _0)]
+    UnknownKey(String),
+    #[fail(display = "Unknown action \"{}\" for key \"{}\" in [keys]", _1, _0)]
+    UnknownAction(String, String),
+    #[fail(display = "Keys \"{}\" and \"{}\" in [keys] are the same key", _0, _1)]
+    Conflict(String, String),
+}
+
+/// Something a key can be bound to outside of the sidebar, switcher and selection mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    /// Opens the channel switcher, or kills to the end of the line while composing
+    SwitchChannel,
+    ToggleSidebar,
+    FocusSidebar,
+    NextChannel,
+    PrevChannel,
+    SelectMessages,
+    Submit,
+    Newline,
+    /// Leaves command mode or abandons an edit
+    Cancel,
+    /// Completes the word before the cursor, or focuses the sidebar if there is nothing to complete
+    Complete,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    /// Moves to the start of the line, or scrolls to the oldest message when the input is empty
+    LineStart,
+    /// Moves to the end of the line, or scrolls to the newest message when the input is empty
+    LineEnd,
+    /// Moves up a line, or recalls older history from the first line
+    LineUp,
+    /// Moves down a line, or recalls newer history from the last line
+    LineDown,
+    KillWordBack,
+    KillToEnd,
+    KillToStart,
+    ClearLine,
+    Yank,
+    YankPop,
+    ScrollPageUp,
+    ScrollPageDown,
+    ScrollTop,
+    ScrollBottom,
+}
+
+/// Action names as written in config.toml
+const ACTIONS: &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("switch_channel", Action::SwitchChannel),
+    ("toggle_sidebar", Action::ToggleSidebar),
+    ("focus_sidebar", Action::FocusSidebar),
+    ("next_channel", Action::NextChannel),
+    ("prev_channel", Action::PrevChannel),
+    ("select_messages", Action::SelectMessages),
+    ("submit", Action::Submit),
+    ("newline", Action::Newline),
+    ("cancel", Action::Cancel),
+    ("complete", Action::Complete),
+    ("backspace", Action::Backspace),
+    ("delete", Action::Delete),
+    ("left", Action::Left),
+    ("right", Action::Right),
+    ("word_left", Action::WordLeft),
+    ("word_right", Action::WordRight),
+    ("line_start", Action::LineStart),
+    ("line_end", Action::LineEnd),
+    ("line_up", Action::LineUp),
+    ("line_down", Action::LineDown),
+    ("kill_word_back", Action::KillWordBack),
+    ("kill_to_end", Action::KillToEnd),
+    ("kill_to_start", Action::KillToStart),
+    ("clear_line", Action::ClearLine),
+    ("yank", Action::Yank),
+    ("yank_pop", Action::YankPop),
+    ("scroll_page_up", Action::ScrollPageUp),
+    ("scroll_page_down", Action::ScrollPageDown),
+    ("scroll_top", Action::ScrollTop),
+    ("scroll_bottom", Action::ScrollBottom),
+];
+
+const DEFAULTS: &[(Key, Action)] = &[
+    (Key::Ctrl('c'), Action::Quit),
+    (Key::Ctrl('d'), Action::Quit),
+    (Key::Ctrl('k'), Action::SwitchChannel),
+    (Key::Alt('s'), Action::ToggleSidebar),
+    (Key::Alt('n'), Action::NextChannel),
+    (Key::Alt('p'), Action::PrevChannel),
+    (Key::Ctrl('s'), Action::SelectMessages),
+    (Key::Char('\n'), Action::Submit),
+    (Key::Alt('\r'), Action::Newline),
+    (Key::Esc, Action::Cancel),
+    (Key::Char('\t'), Action::Complete),
+    (Key::Backspace, Action::Backspace),
+    (Key::Delete, Action::Delete),
+    (Key::Left, Action::Left),
+    (Key::Ctrl('b'), Action::Left),
+    (Key::Right, Action::Right),
+    (Key::Ctrl('f'), Action::Right),
+    (Key::Alt('b'), Action::WordLeft),
+    (Key::Alt('f'), Action::WordRight),
+    (Key::Home, Action::LineStart),
+    (Key::Ctrl('a'), Action::LineStart),
+    (Key::End, Action::LineEnd),
+    (Key::Ctrl('e'), Action::LineEnd),
+    (Key::Up, Action::LineUp),
+    (Key::Down, Action::LineDown),
+    (Key::Ctrl('w'), Action::KillWordBack),
+    (Key::Ctrl('u'), Action::KillToStart),
+    (Key::Ctrl('y'), Action::Yank),
+    (Key::Alt('y'), Action::YankPop),
+    (Key::PageUp, Action::ScrollPageUp),
+    (Key::PageDown, Action::ScrollPageDown),
+];
+
+/// Parses a key name such as `ctrl-k`, `alt-enter`, `pageup` or `G`
+pub fn parse_key(name: &str) -> Option<Key> {
+    let lower = name.to_lowercase();
+    if lower.starts_with("ctrl-") {
+        let mut chars = lower["ctrl-".len()..].chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(Key::Ctrl(c)),
+            _ => None,
+        };
+    }
+    if lower.starts_with("alt-") {
+        let rest = &name["alt-".len()..];
+        let mut chars = rest.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(Key::Alt(c)),
+            _ => match parse_key(rest) {
+                // Terminals report Alt-Enter as Escape followed by a carriage return
+                Some(Key::Char('\n')) => Some(Key::Alt('\r')),
+                Some(Key::Char(c)) => Some(Key::Alt(c)),
+                _ => None,
+            },
+        };
+    }
+
+    let key = match lower.as_str() {
+        "enter" | "return" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        "esc" | "escape" => Key::Esc,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "insert" => Key::Insert,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "backtab" => Key::BackTab,
+        _ => {
+            if lower.starts_with('f') && lower.len() > 1 {
+                let number = lower[1..].parse().ok().filter(|n| *n >= 1 && *n <= 12)?;
+                Key::F(number)
+            } else {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Key::Char(c),
+                    _ => return None,
+                }
+            }
+        }
+    };
+    Some(key)
+}
+
+/// Maps keys to actions, starting from the defaults and applying the `[keys]` table
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap {
+            bindings: DEFAULTS.to_vec(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Applies bindings from config.toml, where the action `none` unbinds a key
+    pub fn from_config(keys: &HashMap<String, String>) -> Result<Keymap, KeymapError> {
+        let mut keymap = Keymap::default();
+
+        let mut entries = keys.iter().collect::<Vec<_>>();
+        entries.sort();
+        let mut seen: Vec<(Key, &str)> = Vec::new();
+        for (name, action_name) in entries {
+            let key = parse_key(name).ok_or_else(|| KeymapError::UnknownKey(name.clone()))?;
+            if let Some((_, other)) = seen.iter().find(|(seen_key, _)| *seen_key == key) {
+                return Err(KeymapError::Conflict((*other).to_owned(), name.clone()));
+            }
+            seen.push((key, name));
+
+            keymap.bindings.retain(|(bound, _)| *bound != key);
+            if action_name != "none" {
+                let action = ACTIONS
+                    .iter()
+                    .find(|(action, _)| action == action_name)
+                    .map(|(_, action)| *action)
+                    .ok_or_else(|| KeymapError::UnknownAction(name.clone(), action_name.clone()))?;
+                keymap.bindings.push((key, action));
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        let key = match key {
+            Key::Alt('\n') => Key::Alt('\r'),
+            key => key,
+        };
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == key)
+            .map(|(_, action)| *action)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, action)| ((*key).to_owned(), (*action).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(parse_key("ctrl-K"), Some(Key::Ctrl('k')));
+        assert_eq!(parse_key("alt-enter"), Some(Key::Alt('\r')));
+        assert_eq!(parse_key("alt-B"), Some(Key::Alt('B')));
+        assert_eq!(parse_key("PageUp"), Some(Key::PageUp));
+        assert_eq!(parse_key("f5"), Some(Key::F(5)));
+        assert_eq!(parse_key("G"), Some(Key::Char('G')));
+        assert_eq!(parse_key("ctrl-"), None);
+        assert_eq!(parse_key("hyper-x"), None);
+        assert_eq!(parse_key("f13"), None);
+    }
+
+    #[test]
+    fn overrides_defaults() {
+        let keymap =
+            Keymap::from_config(&config(&[("ctrl-q", "quit"), ("ctrl-c", "none")])).unwrap();
+        assert_eq!(keymap.action(Key::Ctrl('q')), Some(Action::Quit));
+        assert_eq!(keymap.action(Key::Ctrl('c')), None);
+        assert_eq!(keymap.action(Key::Ctrl('d')), Some(Action::Quit));
+        assert_eq!(keymap.action(Key::Alt('\n')), Some(Action::Newline));
+    }
+
+    #[test]
+    fn rejects_bad_bindings() {
+        match Keymap::from_config(&config(&[("enter", "submit"), ("return", "newline")])) {
+            Err(KeymapError::Conflict(..)) => {}
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        match Keymap::from_config(&config(&[("ctrl-q", "explode")])) {
+            Err(KeymapError::UnknownAction(..)) => {}
+            other => panic!("expected an unknown action, got {:?}", other),
+        }
+        match Keymap::from_config(&config(&[("meta-q", "quit")])) {
+            Err(KeymapError::UnknownKey(..)) => {}
+            other => panic!("expected an unknown key, got {:?}", other),
+        }
+    }
+}
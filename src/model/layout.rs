@@ -1,5 +1,3 @@
-#![allow(dead_code)]
-
 use std::cmp::{max, min};
 
 /// A simple rectangle used in the computation of the layout and to give widgets an hint about the
@@ -87,15 +85,140 @@ impl Rect {
         Rect {
             x: x1,
             y: y1,
-            width: x2 - x1,
-            height: y2 - y1,
+            width: x2.saturating_sub(x1),
+            height: y2.saturating_sub(y1),
         }
     }
 
-    pub fn intersects(&self, other: &Rect) -> bool {
-        self.x < other.x + other.width
-            && self.x + self.width > other.x
-            && self.y < other.y + other.height
-            && self.y + self.height > other.y
+    /// Splits off the top `height` rows, returning them and the rest
+    pub fn split_top(&self, height: usize) -> (Rect, Rect) {
+        let height = min(height, self.height);
+        (
+            Rect { height, ..*self },
+            Rect {
+                y: self.y + height,
+                height: self.height - height,
+                ..*self
+            },
+        )
+    }
+
+    /// Splits off the bottom `height` rows, returning the rest and them
+    pub fn split_bottom(&self, height: usize) -> (Rect, Rect) {
+        self.split_top(self.height.saturating_sub(height))
+    }
+
+    /// Splits off the leftmost `width` columns, returning them and the rest
+    pub fn split_left(&self, width: usize) -> (Rect, Rect) {
+        let width = min(width, self.width);
+        (
+            Rect { width, ..*self },
+            Rect {
+                x: self.x + width,
+                width: self.width - width,
+                ..*self
+            },
+        )
+    }
+}
+
+/// Columns kept clear between the edges of the screen and the input box
+const MARGIN: usize = 2;
+
+/// Where each part of the interface is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// The guild list, when it is shown
+    pub sidebar: Option<Rect>,
+    /// The open channel's name, above the messages
+    pub header: Rect,
+    /// Messages, with the bottom row kept for the scroll marker
+    pub messages: Rect,
+    /// The input box, including its outline
+    pub input: Rect,
+    /// Who is typing, below the input box
+    pub indicator: Rect,
+}
+
+impl Layout {
+    /// Splits the screen into panes, with `input_rows` rows of text in the input box
+    pub fn new(screen: Rect, sidebar_width: Option<usize>, input_rows: usize) -> Layout {
+        let (rest, indicator) = screen.split_bottom(1);
+        let (rest, input) = rest.split_bottom(input_rows + 2);
+
+        let columns = Rect {
+            x: screen.x + MARGIN,
+            width: screen.width.saturating_sub(2 * MARGIN),
+            ..screen
+        };
+        // Columns the text inside the input box is drawn in
+        let text_columns = Rect {
+            x: columns.x + 1,
+            width: columns.width.saturating_sub(2),
+            ..screen
+        };
+
+        let (sidebar, main) = match sidebar_width {
+            Some(width) => {
+                let (sidebar, main) = rest.split_left(width);
+                // The sidebar starts below the header row, which stays blank above it
+                (Some(sidebar.split_top(1).1), main.intersection(&columns))
+            }
+            None => (None, rest.intersection(&text_columns)),
+        };
+        let (header, messages) = main.split_top(1);
+
+        Layout {
+            sidebar,
+            header,
+            messages,
+            input: input.intersection(&columns),
+            indicator: indicator.intersection(&text_columns),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn splits_rects() {
+        let rect = Rect::new(2, 3, 10, 4);
+        assert_eq!(
+            rect.split_top(1),
+            (Rect::new(2, 3, 10, 1), Rect::new(2, 4, 10, 3))
+        );
+        assert_eq!(
+            rect.split_bottom(5),
+            (Rect::new(2, 3, 10, 0), Rect::new(2, 3, 10, 4))
+        );
+        assert_eq!(
+            rect.split_left(4),
+            (Rect::new(2, 3, 4, 4), Rect::new(6, 3, 6, 4))
+        );
+        assert_eq!(rect.intersection(&Rect::new(20, 0, 5, 5)).area(), 0);
+    }
+
+    #[test]
+    fn lays_out_panes() {
+        let layout = Layout::new(Rect::new(0, 0, 100, 40), Some(28), 1);
+        assert_eq!(layout.indicator, Rect::new(3, 39, 94, 1));
+        assert_eq!(layout.input, Rect::new(2, 36, 96, 3));
+        assert_eq!(layout.sidebar, Some(Rect::new(0, 1, 28, 35)));
+        assert_eq!(layout.header, Rect::new(28, 0, 70, 1));
+        assert_eq!(layout.messages, Rect::new(28, 1, 70, 35));
+
+        let layout = Layout::new(Rect::new(0, 0, 100, 40), None, 3);
+        assert_eq!(layout.input, Rect::new(2, 34, 96, 5));
+        assert_eq!(layout.sidebar, None);
+        assert_eq!(layout.messages, Rect::new(3, 1, 94, 33));
+    }
+
+    #[test]
+    fn fits_small_screens() {
+        let layout = Layout::new(Rect::new(0, 0, 3, 2), Some(28), 5);
+        assert_eq!(layout.messages.area(), 0);
+        assert_eq!(layout.input.area(), 0);
     }
 }
@@ -3,4 +3,6 @@ use serenity::model::channel;
 #[derive(Clone, Debug)]
 pub enum MessageItem {
     DiscordMessage(Box<channel::Message>),
+    /// Local feedback that is never sent to Discord
+    Notice(String),
 }
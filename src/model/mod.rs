@@ -1,15 +1,25 @@
 pub mod application;
 mod context;
 mod event;
+mod history;
+mod keymap;
 pub mod layout;
 pub mod message;
+mod notifications;
 mod preferences;
 mod state;
+mod theme;
+mod unread;
 
 pub use self::application::Application;
 pub use self::context::Context;
-pub use self::event::Event;
-pub use self::layout::Rect;
+pub use self::event::{Event, Scroll};
+pub use self::history::History;
+pub use self::keymap::{Action, Keymap};
+pub use self::layout::{Layout, Rect};
 pub use self::message::MessageItem;
-pub use self::preferences::Preferences;
+pub use self::notifications::Notifications;
+pub use self::preferences::{expand_home, Preferences};
 pub use self::state::State;
+pub use self::theme::{Look, Theme};
+pub use self::unread::Unread;
@@ -0,0 +1,218 @@
+use chrono::NaiveTime;
+use failure::Error;
+use serenity::model::id::{ChannelId, GuildId};
+
+use std::collections::HashMap;
+
+use helpers::notify::Backend;
+
+#[derive(Fail, Debug)]
+#[fail(
+    display = "Invalid quiet hours \"{}\", expected a range like \"23:00-07:00\"",
+    _0
+)]
+pub struct QuietHoursError(pub String);
+
+#[derive(Fail, Debug)]
+#[fail(display = "The command notification backend needs `notifications.command` to be set")]
+pub struct MissingCommandError;
+
+/// Which messages in a guild or channel notify
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    All,
+    Mentions,
+    None,
+}
+
+/// When to notify about new messages, the `[notifications]` table of config.toml
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Notifications {
+    /// Level of guild channels without an override
+    pub level: Level,
+    /// Whether direct messages and group DMs notify
+    pub direct_messages: bool,
+    /// Words that notify like a mention, ignoring case
+    pub keywords: Vec<String>,
+    /// Levels keyed by guild id
+    pub guilds: HashMap<String, Level>,
+    /// Levels keyed by channel id, taking precedence over the guild's
+    pub channels: HashMap<String, Level>,
+    /// A range such as "23:00-07:00" during which nothing notifies
+    pub quiet_hours: Option<String>,
+    /// How notifications are delivered, several may be used at once
+    pub backends: Vec<Backend>,
+    /// Program and arguments run by the command backend
+    pub command: Vec<String>,
+    /// Whether the command receives JSON on stdin rather than sender, channel and body arguments
+    pub command_json: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Notifications {
+        Notifications {
+            level: Level::Mentions,
+            direct_messages: true,
+            keywords: Vec::new(),
+            guilds: HashMap::new(),
+            channels: HashMap::new(),
+            quiet_hours: None,
+            backends: vec![Backend::Desktop],
+            command: Vec::new(),
+            command_json: false,
+        }
+    }
+}
+
+fn parse_quiet_hours(range: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let mut parts = range.splitn(2, '-');
+    let start = NaiveTime::parse_from_str(parts.next()?.trim(), "%H:%M").ok()?;
+    let end = NaiveTime::parse_from_str(parts.next()?.trim(), "%H:%M").ok()?;
+    Some((start, end))
+}
+
+/// Whether `word` appears in `text` on its own, ignoring case
+fn contains_word(text: &str, word: &str) -> bool {
+    let text = text.to_lowercase();
+    let word = word.to_lowercase();
+    if word.is_empty() {
+        return false;
+    }
+    let boundary = |c: Option<char>| !c.map_or(false, char::is_alphanumeric);
+    text.match_indices(&word).any(|(i, _)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + word.len()..].chars().next();
+        boundary(before) && boundary(after)
+    })
+}
+
+impl Notifications {
+    /// Checks the settings that are not checked while deserializing
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(ref range) = self.quiet_hours {
+            if parse_quiet_hours(range).is_none() {
+                Err(QuietHoursError(range.clone()))?
+            }
+        }
+        if self.backends.contains(&Backend::Command) && self.command.is_empty() {
+            Err(MissingCommandError)?
+        }
+        Ok(())
+    }
+
+    fn is_quiet(&self, time: NaiveTime) -> bool {
+        match self
+            .quiet_hours
+            .as_ref()
+            .and_then(|range| parse_quiet_hours(range))
+        {
+            Some((start, end)) if start <= end => start <= time && time < end,
+            Some((start, end)) => time >= start || time < end,
+            None => false,
+        }
+    }
+
+    /// The level of a channel, `guild` being `None` for private channels
+    fn level(&self, guild: Option<GuildId>, channel: ChannelId) -> Level {
+        if let Some(level) = self.channels.get(&channel.0.to_string()) {
+            return *level;
+        }
+        match guild {
+            Some(guild) => *self.guilds.get(&guild.0.to_string()).unwrap_or(&self.level),
+            None if self.direct_messages => Level::All,
+            None => Level::None,
+        }
+    }
+
+    /// Whether a message from someone else in a channel that is not open should notify
+    pub fn should_notify(
+        &self,
+        guild: Option<GuildId>,
+        channel: ChannelId,
+        mentioned: bool,
+        content: &str,
+        time: NaiveTime,
+    ) -> bool {
+        if self.is_quiet(time) {
+            return false;
+        }
+        match self.level(guild, channel) {
+            Level::All => true,
+            Level::Mentions => {
+                mentioned
+                    || self
+                        .keywords
+                        .iter()
+                        .any(|word| contains_word(content, word))
+            }
+            Level::None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn noon() -> NaiveTime {
+        NaiveTime::from_hms(12, 0, 0)
+    }
+
+    #[test]
+    fn default_levels() {
+        let notifications = Notifications::default();
+        let guild = Some(GuildId(1));
+
+        assert!(!notifications.should_notify(guild, ChannelId(2), false, "hi", noon()));
+        assert!(notifications.should_notify(guild, ChannelId(2), true, "hi", noon()));
+        assert!(notifications.should_notify(None, ChannelId(3), false, "hi", noon()));
+    }
+
+    #[test]
+    fn overrides() {
+        let mut notifications = Notifications::default();
+        notifications.guilds.insert("1".to_owned(), Level::All);
+        notifications.channels.insert("2".to_owned(), Level::None);
+        notifications.direct_messages = false;
+
+        assert!(notifications.should_notify(Some(GuildId(1)), ChannelId(4), false, "", noon()));
+        assert!(!notifications.should_notify(Some(GuildId(1)), ChannelId(2), true, "", noon()));
+        assert!(!notifications.should_notify(None, ChannelId(3), false, "", noon()));
+    }
+
+    #[test]
+    fn keywords() {
+        let mut notifications = Notifications::default();
+        notifications.keywords.push("Dex".to_owned());
+        let guild = Some(GuildId(1));
+
+        assert!(notifications.should_notify(guild, ChannelId(2), false, "is dex up?", noon()));
+        assert!(!notifications.should_notify(guild, ChannelId(2), false, "index", noon()));
+    }
+
+    #[test]
+    fn quiet_hours() {
+        let mut notifications = Notifications::default();
+        notifications.quiet_hours = Some("23:00-07:00".to_owned());
+        assert!(notifications.validate().is_ok());
+
+        let late = NaiveTime::from_hms(23, 30, 0);
+        assert!(!notifications.should_notify(None, ChannelId(3), true, "", late));
+        assert!(notifications.should_notify(None, ChannelId(3), true, "", noon()));
+
+        notifications.quiet_hours = Some("soon".to_owned());
+        assert!(notifications.validate().is_err());
+    }
+
+    #[test]
+    fn command_backend_needs_command() {
+        let mut notifications = Notifications::default();
+        notifications.backends.push(Backend::Command);
+        assert!(notifications.validate().is_err());
+
+        notifications.command.push("notify-send".to_owned());
+        assert!(notifications.validate().is_ok());
+    }
+}
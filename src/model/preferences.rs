@@ -1,17 +1,36 @@
 use failure::Error;
 use toml;
 
+use std::collections::HashMap;
 use std::env;
 use std::fs::OpenOptions;
 use std::io::Read;
+use std::path::PathBuf;
 
 use error::*;
+use helpers::chars::CharSetType;
+use model::Notifications;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Preferences {
     pub token: String,
     pub timestamp_fmt: Option<String>,
     pub nerd_fonts: Option<bool>,
+    /// `unicode`, `nerd` or `ascii`, taking precedence over `nerd_fonts`
+    pub charset: Option<CharSetType>,
+    /// Name of a theme in ~/.config/dex/themes
+    pub theme: Option<String>,
+    /// Rows the input box may grow to while composing a multi-line message
+    pub input_rows: Option<usize>,
+    /// Columns taken up by the guild sidebar
+    pub sidebar_width: Option<usize>,
+    /// Directory attachments are saved to, ~/Downloads by default
+    pub download_dir: Option<String>,
+    #[serde(default)]
+    pub notifications: Notifications,
+    /// Key names mapped to action names, overriding the default bindings
+    #[serde(default)]
+    pub keys: HashMap<String, String>,
 }
 
 impl Preferences {
@@ -24,6 +43,7 @@ impl Preferences {
         file.read_to_string(&mut buf)?;
 
         let preferences: Preferences = toml::from_str(&buf)?;
+        preferences.notifications.validate()?;
         Ok(preferences)
     }
 
@@ -36,4 +56,24 @@ impl Preferences {
     pub fn nerd_fonts(&self) -> bool {
         self.nerd_fonts.unwrap_or(false)
     }
+
+    pub fn input_rows(&self) -> usize {
+        self.input_rows.unwrap_or(5).max(1)
+    }
+
+    pub fn sidebar_width(&self) -> usize {
+        self.sidebar_width.unwrap_or(28)
+    }
+
+    pub fn download_dir(&self) -> PathBuf {
+        expand_home(self.download_dir.as_ref().map_or("~/Downloads", |dir| dir.as_str()))
+    }
+}
+
+/// Expands a leading `~` to the user's home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.starts_with('~'), env::home_dir()) {
+        (true, Some(home)) => home.join(path[1..].trim_left_matches('/')),
+        _ => PathBuf::from(path),
+    }
 }
@@ -3,6 +3,7 @@ use toml;
 
 use serenity::model::id::{ChannelId, GuildId};
 
+use std::collections::HashMap;
 use std::env;
 use std::fs::OpenOptions;
 use std::io::{Read, Write};
@@ -15,6 +16,9 @@ pub struct State {
     pub channel: Option<ChannelId>,
     #[serde(default = "_true")]
     pub guild_sidebar_visible: bool,
+    /// Newest read message per channel, keyed by channel id since TOML keys must be strings
+    #[serde(default)]
+    pub last_read: HashMap<String, u64>,
 }
 
 fn _true() -> bool {
@@ -41,6 +45,7 @@ impl State {
         let mut file = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(home_dir.join(".config/dex/persistent_state.toml"))?;
 
         let data = toml::to_string(self)?;
@@ -61,5 +66,6 @@ mod test {
         assert!(state.guild.is_none());
         assert!(state.channel.is_none());
         assert_eq!(state.guild_sidebar_visible, true);
+        assert!(state.last_read.is_empty());
     }
 }
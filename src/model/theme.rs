@@ -0,0 +1,307 @@
+use failure::Error;
+use termbuf::Color;
+use toml;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Read;
+
+use error::*;
+
+#[derive(Fail, Debug)]
+#[fail(
+    display = "Invalid colour \"{}\" in theme, expected a name, a number up to 255 or #rrggbb",
+    _0
+)]
+pub struct InvalidColourError(pub String);
+
+const COLOUR_NAMES: &[&str] = &[
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright-black",
+    "bright-red",
+    "bright-green",
+    "bright-yellow",
+    "bright-blue",
+    "bright-magenta",
+    "bright-cyan",
+    "bright-white",
+];
+
+/// Parses a colour name, an ANSI colour number or `#rrggbb`, with `none` meaning no colour
+fn parse_colour(text: &str) -> Result<Option<Color>, InvalidColourError> {
+    let invalid = || InvalidColourError(text.to_owned());
+    let lower = text.trim().to_lowercase();
+    if lower == "none" {
+        return Ok(None);
+    }
+    if let Some(index) = COLOUR_NAMES.iter().position(|name| *name == lower) {
+        return Ok(Some(Color::AnsiValue(index as u8)));
+    }
+    if lower.starts_with('#') {
+        let hex = &lower[1..];
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        return Ok(Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?)));
+    }
+    lower
+        .parse()
+        .map(|value| Some(Color::AnsiValue(value)))
+        .map_err(|_| invalid())
+}
+
+/// How one part of the interface is drawn
+#[derive(Clone, Copy, Default)]
+pub struct Look {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl Look {
+    fn fg(fg: Color) -> Look {
+        Look {
+            fg: Some(fg),
+            ..Look::default()
+        }
+    }
+
+    fn bg(bg: Color) -> Look {
+        Look {
+            bg: Some(bg),
+            ..Look::default()
+        }
+    }
+
+    /// Draws `other` on top of this look, its colours replacing ours
+    pub fn merge(self, other: Look) -> Look {
+        Look {
+            fg: other.fg.or(self.fg),
+            bg: other.bg.or(self.bg),
+            bold: self.bold || other.bold,
+            faint: self.faint || other.faint,
+            italic: self.italic || other.italic,
+            underline: self.underline || other.underline,
+            strikethrough: self.strikethrough || other.strikethrough,
+        }
+    }
+}
+
+/// A look as written in a theme file, where unset fields keep the default
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LookEntry {
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: Option<bool>,
+    faint: Option<bool>,
+    italic: Option<bool>,
+    underline: Option<bool>,
+    strikethrough: Option<bool>,
+}
+
+impl LookEntry {
+    fn apply(&self, look: &mut Look) -> Result<(), InvalidColourError> {
+        if let Some(ref fg) = self.fg {
+            look.fg = parse_colour(fg)?;
+        }
+        if let Some(ref bg) = self.bg {
+            look.bg = parse_colour(bg)?;
+        }
+        look.bold = self.bold.unwrap_or(look.bold);
+        look.faint = self.faint.unwrap_or(look.faint);
+        look.italic = self.italic.unwrap_or(look.italic);
+        look.underline = self.underline.unwrap_or(look.underline);
+        look.strikethrough = self.strikethrough.unwrap_or(look.strikethrough);
+        Ok(())
+    }
+}
+
+/// Colours and styles used throughout the interface
+#[derive(Clone)]
+pub struct Theme {
+    pub timestamp: Look,
+    /// Nicks of users without a role colour
+    pub nick: Look,
+    pub notice: Look,
+    /// Box outlines and the line beside the sidebar
+    pub border: Look,
+    /// Guild and section headings in the sidebar
+    pub guild: Look,
+    /// The open channel in the sidebar
+    pub selected_channel: Look,
+    pub unread: Look,
+    /// Mention counts in the sidebar
+    pub mention: Look,
+    /// The highlighted row of the sidebar and channel switcher
+    pub cursor: Look,
+    /// The message highlighted in selection mode
+    pub selected_message: Look,
+    /// The marker shown when scrolled up from the newest message
+    pub scroll_marker: Look,
+    pub code: Look,
+    pub link: Look,
+    pub quote: Look,
+    pub spoiler: Look,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        let bold = Look {
+            bold: true,
+            ..Look::default()
+        };
+        let faint = Look {
+            faint: true,
+            ..Look::default()
+        };
+        Theme {
+            timestamp: faint,
+            nick: Look::default(),
+            notice: faint,
+            border: Look::default(),
+            guild: bold,
+            selected_channel: Look {
+                underline: true,
+                ..Look::default()
+            },
+            unread: bold,
+            mention: Look {
+                fg: Some(Color::AnsiValue(203)),
+                bold: true,
+                ..Look::default()
+            },
+            cursor: Look::bg(Color::AnsiValue(238)),
+            selected_message: Look::bg(Color::AnsiValue(237)),
+            scroll_marker: bold,
+            code: Look::fg(Color::AnsiValue(173)),
+            link: Look {
+                fg: Some(Color::AnsiValue(75)),
+                underline: true,
+                ..Look::default()
+            },
+            quote: faint,
+            spoiler: Look {
+                fg: Some(Color::AnsiValue(238)),
+                bg: Some(Color::AnsiValue(238)),
+                ..Look::default()
+            },
+        }
+    }
+}
+
+impl Theme {
+    /// Loads `~/.config/dex/themes/<name>.toml` over the default theme
+    pub fn load(name: &str) -> Result<Theme, Error> {
+        let home_dir = env::home_dir().ok_or(HomeDirError)?;
+        let path = home_dir
+            .join(".config/dex/themes")
+            .join(format!("{}.toml", name));
+        let mut file = OpenOptions::new()
+            .read(true)
+            .open(&path)
+            .map_err(|err| format_err!("Unable to open theme {}: {}", path.display(), err))?;
+        let mut buf = String::new();
+        file.read_to_string(&mut buf)?;
+        Theme::from_toml(&buf)
+    }
+
+    fn from_toml(text: &str) -> Result<Theme, Error> {
+        let entries: HashMap<String, LookEntry> = toml::from_str(text)?;
+        let mut theme = Theme::default();
+        for (name, entry) in &entries {
+            let look = theme
+                .look_mut(name)
+                .ok_or_else(|| format_err!("Unknown theme entry [{}]", name))?;
+            entry.apply(look)?;
+        }
+        Ok(theme)
+    }
+
+    fn look_mut(&mut self, name: &str) -> Option<&mut Look> {
+        let look = match name {
+            "timestamp" => &mut self.timestamp,
+            "nick" => &mut self.nick,
+            "notice" => &mut self.notice,
+            "border" => &mut self.border,
+            "guild" => &mut self.guild,
+            "selected_channel" => &mut self.selected_channel,
+            "unread" => &mut self.unread,
+            "mention" => &mut self.mention,
+            "cursor" => &mut self.cursor,
+            "selected_message" => &mut self.selected_message,
+            "scroll_marker" => &mut self.scroll_marker,
+            "code" => &mut self.code,
+            "link" => &mut self.link,
+            "quote" => &mut self.quote,
+            "spoiler" => &mut self.spoiler,
+            _ => return None,
+        };
+        Some(look)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_colours() {
+        assert!(parse_colour("none").unwrap().is_none());
+        match parse_colour("Bright-Red") {
+            Ok(Some(Color::AnsiValue(9))) => {}
+            _ => panic!("expected ANSI colour 9"),
+        }
+        match parse_colour("#ff8000") {
+            Ok(Some(Color::Rgb(255, 128, 0))) => {}
+            _ => panic!("expected an RGB colour"),
+        }
+        assert!(parse_colour("256").is_err());
+        assert!(parse_colour("#12").is_err());
+        assert!(parse_colour("#aééb").is_err());
+        assert!(parse_colour("#12345g").is_err());
+        assert!(parse_colour("mauve").is_err());
+    }
+
+    #[test]
+    fn overrides_defaults() {
+        let theme = Theme::from_toml(
+            r##"
+            [timestamp]
+            fg = "cyan"
+            faint = false
+
+            [mention]
+            fg = "none"
+            "##,
+        )
+        .unwrap();
+
+        assert!(!theme.timestamp.faint);
+        match theme.timestamp.fg {
+            Some(Color::AnsiValue(6)) => {}
+            _ => panic!("expected cyan timestamps"),
+        }
+        assert!(theme.mention.fg.is_none());
+        assert!(theme.mention.bold);
+        assert!(theme.unread.bold);
+    }
+
+    #[test]
+    fn rejects_unknown_parts() {
+        assert!(Theme::from_toml("[timestamps]\nbold = true").is_err());
+    }
+}
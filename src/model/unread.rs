@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use serenity::model::id::{ChannelId, MessageId};
+
+/// Messages received in a channel since it was last read
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Activity {
+    pub unread: usize,
+    pub mentions: usize,
+}
+
+/// Tracks unread messages and mentions per channel
+#[derive(Debug, Default)]
+pub struct Unread {
+    /// The newest message seen in each channel
+    last_read: HashMap<ChannelId, MessageId>,
+    activity: HashMap<ChannelId, Activity>,
+}
+
+impl Unread {
+    /// Restores the last read messages saved in `State`
+    pub fn from_saved(saved: &HashMap<String, u64>) -> Unread {
+        let last_read = saved
+            .iter()
+            .filter_map(|(channel, message)| {
+                channel
+                    .parse()
+                    .ok()
+                    .map(|channel| (ChannelId(channel), MessageId(*message)))
+            })
+            .collect();
+        Unread {
+            last_read,
+            activity: HashMap::new(),
+        }
+    }
+
+    /// The last read messages in the form saved in `State`
+    pub fn to_saved(&self) -> HashMap<String, u64> {
+        self.last_read
+            .iter()
+            .map(|(channel, message)| (channel.0.to_string(), message.0))
+            .collect()
+    }
+
+    /// Records a message in a channel that is not being read
+    pub fn add_message(&mut self, channel: ChannelId, message: MessageId, mentioned: bool) {
+        if self.last_read.get(&channel).map_or(false, |last| *last >= message) {
+            return;
+        }
+        let activity = self.activity.entry(channel).or_insert_with(Activity::default);
+        activity.unread += 1;
+        if mentioned {
+            activity.mentions += 1;
+        }
+    }
+
+    /// Marks everything up to `message` as read
+    pub fn mark_read(&mut self, channel: ChannelId, message: MessageId) {
+        let last = self.last_read.entry(channel).or_insert(message);
+        if *last < message {
+            *last = message;
+        }
+        self.activity.remove(&channel);
+    }
+
+    pub fn activity(&self, channel: ChannelId) -> Activity {
+        self.activity.get(&channel).cloned().unwrap_or_default()
+    }
+
+    /// Whether a channel has unread messages, including ones sent while dex was closed
+    ///
+    /// Channels that have never been read are not considered unread
+    pub fn is_unread(&self, channel: ChannelId, last_message: Option<MessageId>) -> bool {
+        if self.activity(channel).unread > 0 {
+            return true;
+        }
+        match (self.last_read.get(&channel), last_message) {
+            (Some(last_read), Some(last_message)) => last_message > *last_read,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counts_until_read() {
+        let mut unread = Unread::default();
+        unread.add_message(ChannelId(1), MessageId(10), false);
+        unread.add_message(ChannelId(1), MessageId(11), true);
+
+        assert_eq!(
+            unread.activity(ChannelId(1)),
+            Activity {
+                unread: 2,
+                mentions: 1,
+            }
+        );
+
+        unread.mark_read(ChannelId(1), MessageId(11));
+        assert_eq!(unread.activity(ChannelId(1)), Activity::default());
+        assert!(!unread.is_unread(ChannelId(1), Some(MessageId(11))));
+        assert!(unread.is_unread(ChannelId(1), Some(MessageId(12))));
+    }
+
+    #[test]
+    fn ignores_read_messages() {
+        let mut unread = Unread::default();
+        unread.mark_read(ChannelId(1), MessageId(10));
+        unread.add_message(ChannelId(1), MessageId(9), true);
+
+        assert_eq!(unread.activity(ChannelId(1)), Activity::default());
+    }
+
+    #[test]
+    fn saved_round_trip() {
+        let mut unread = Unread::default();
+        unread.mark_read(ChannelId(1), MessageId(10));
+
+        let restored = Unread::from_saved(&unread.to_saved());
+        assert!(restored.is_unread(ChannelId(1), Some(MessageId(11))));
+    }
+}
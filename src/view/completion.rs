@@ -0,0 +1,162 @@
+use serenity::model::channel::ChannelType;
+use serenity::model::id::GuildId;
+
+use command::CommandHandler;
+use helpers::emoji::SHORTCODES;
+
+/// What the word being completed refers to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    User,
+    Channel,
+    Emoji,
+    Command,
+}
+
+/// The partial word before the cursor
+#[derive(Debug, PartialEq)]
+pub struct Word<'a> {
+    pub kind: Kind,
+    /// Byte offset of the word, including its sigil
+    pub start: usize,
+    /// The typed text after the sigil
+    pub query: &'a str,
+}
+
+/// A possible completion
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// Text inserted into the input
+    pub text: String,
+    /// What the inserted text is replaced with when the message is sent
+    pub replacement: Option<String>,
+}
+
+/// Finds the completable word ending at `cursor`
+pub fn word_at(text: &str, cursor: usize) -> Option<Word> {
+    let before = &text[..cursor];
+    let start = before
+        .rfind(char::is_whitespace)
+        .map_or(0, |i| i + before[i..].chars().next().unwrap().len_utf8());
+    let word = &before[start..];
+
+    let mut chars = word.chars();
+    let kind = match chars.next()? {
+        ':' if start == 0 => Kind::Command,
+        ':' if !chars.as_str().is_empty() && !chars.as_str().contains(':') => Kind::Emoji,
+        '@' => Kind::User,
+        '#' => Kind::Channel,
+        _ => return None,
+    };
+    Some(Word {
+        kind,
+        start,
+        query: chars.as_str(),
+    })
+}
+
+fn matches(name: &str, query: &str) -> bool {
+    name.to_lowercase().starts_with(&query.to_lowercase())
+}
+
+/// Collects the completions of a word, shortest first
+pub fn candidates(
+    word: &Word,
+    guild_id: Option<GuildId>,
+    commands: &CommandHandler,
+) -> Vec<Candidate> {
+    let guild = guild_id.and_then(|guild_id| guild_id.find());
+    let mut candidates = Vec::new();
+
+    match word.kind {
+        Kind::User => if let Some(guild) = guild {
+            for (id, member) in &guild.read().members {
+                let name = member
+                    .nick
+                    .clone()
+                    .unwrap_or_else(|| member.user.read().name.clone());
+                if matches(&name, word.query) {
+                    candidates.push(Candidate {
+                        text: format!("@{}", name),
+                        replacement: Some(format!("<@{}>", id.0)),
+                    });
+                }
+            }
+        },
+        Kind::Channel => if let Some(guild) = guild {
+            for channel in guild.read().channels.values() {
+                let channel = channel.read();
+                if let ChannelType::Text = channel.kind {
+                    if matches(&channel.name, word.query) {
+                        candidates.push(Candidate {
+                            text: format!("#{}", channel.name),
+                            replacement: Some(format!("<#{}>", channel.id.0)),
+                        });
+                    }
+                }
+            }
+        },
+        Kind::Emoji => {
+            if let Some(guild) = guild {
+                for emoji in guild.read().emojis.values() {
+                    if matches(&emoji.name, word.query) {
+                        let prefix = if emoji.animated { "a" } else { "" };
+                        candidates.push(Candidate {
+                            text: format!(":{}:", emoji.name),
+                            replacement: Some(format!(
+                                "<{}:{}:{}>",
+                                prefix, emoji.name, emoji.id.0
+                            )),
+                        });
+                    }
+                }
+            }
+            for (name, emoji) in SHORTCODES {
+                if matches(name, word.query) {
+                    candidates.push(Candidate {
+                        text: (*emoji).to_owned(),
+                        replacement: None,
+                    });
+                }
+            }
+        }
+        Kind::Command => for command in commands.commands() {
+            if matches(command.name, word.query) {
+                candidates.push(Candidate {
+                    text: format!(":{} ", command.name),
+                    replacement: None,
+                });
+            }
+        },
+    }
+
+    candidates.sort_by(|a, b| a.text.len().cmp(&b.text.len()).then(a.text.cmp(&b.text)));
+    candidates
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_words() {
+        assert_eq!(
+            word_at("hi @ja", 6),
+            Some(Word {
+                kind: Kind::User,
+                start: 3,
+                query: "ja",
+            })
+        );
+        assert_eq!(word_at(":de", 3).map(|word| word.kind), Some(Kind::Command));
+        assert_eq!(word_at("so :sm", 6).map(|word| word.kind), Some(Kind::Emoji));
+        assert_eq!(word_at("see #", 5).map(|word| word.query), Some(""));
+    }
+
+    #[test]
+    fn ignores_plain_words() {
+        assert_eq!(word_at("hello", 5), None);
+        assert_eq!(word_at("a :", 3), None);
+        assert_eq!(word_at("@ja ", 4), None);
+    }
+}
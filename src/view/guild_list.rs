@@ -1,18 +1,24 @@
-use model::Context;
+use discord::utils;
+use helpers::chars::CharSet;
+use model::{Context, Event, Look, Rect};
 use view::terminal::Terminal;
 
 use std::collections::HashMap;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
 use serenity::model::prelude::*;
 use serenity::prelude::*;
 use serenity::CACHE;
-use termbuf::Style;
-use termbuf::TermSize;
+use termbuf::termion::event::Key;
 
-const TOP_START: usize = 2;
-const LEFT_START: usize = 2;
-const MAX_LEN: usize = 28;
+use failure::Error;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Columns between the edge of the sidebar and channel names
+const PADDING: usize = 2;
+/// Rows moved by PageUp and PageDown
+const PAGE: usize = 10;
 
 #[derive(Debug)]
 struct GuildEntry {
@@ -46,27 +52,171 @@ impl GuildEntry {
     }
 }
 
-fn truncate(s: String, new_len: usize) -> String {
-    if s.len() < new_len {
-        s
-    } else {
-        s.chars()
-            .take(new_len.saturating_sub(1))
-            .collect::<String>() + "…"
+/// Shortens `s` to at most `new_len` columns, ending it with `ellipsis` when cut
+fn truncate(s: String, new_len: usize, ellipsis: &str) -> String {
+    if s.width() <= new_len {
+        return s;
     }
+    let room = new_len.saturating_sub(ellipsis.width());
+    let mut used = 0;
+    let mut out: String = s
+        .chars()
+        .take_while(|ch| {
+            used += ch.width().unwrap_or(0);
+            used <= room
+        })
+        .collect();
+    out.push_str(ellipsis);
+    out
+}
+
+/// A single line of the sidebar
+enum Row {
+    Guild(Arc<RwLock<Guild>>),
+    Category(Arc<RwLock<GuildChannel>>),
+    Channel {
+        channel: Arc<RwLock<GuildChannel>>,
+        indent: usize,
+    },
+    /// Heading of the direct messages section
+    DirectMessages,
+    Private {
+        id: ChannelId,
+        name: String,
+        last_message: Option<MessageId>,
+    },
 }
 
 pub struct GuildList {
     guild_list: Vec<GuildEntry>,
+    /// Direct messages and group DMs, most recently active first
+    private_channels: Vec<(ChannelId, String, Option<MessageId>)>,
+    rows: Vec<Row>,
+    /// Whether keys go to the sidebar instead of the input box
+    focused: bool,
+    cursor: usize,
+    char_set: CharSet,
+    event_channel: Sender<Event>,
 }
 
 impl GuildList {
-    pub fn new() -> GuildList {
+    pub fn new(char_set: CharSet, event_channel: Sender<Event>) -> GuildList {
         GuildList {
             guild_list: Vec::new(),
+            private_channels: Vec::new(),
+            rows: Vec::new(),
+            focused: false,
+            cursor: 0,
+            char_set,
+            event_channel,
+        }
+    }
+
+    pub fn focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Moves keyboard focus to the sidebar, placing the cursor on the current channel
+    pub fn focus(&mut self, current_channel: Option<ChannelId>) {
+        self.focused = true;
+        let current = self.rows.iter().position(|row| match row {
+            Row::Channel { channel, .. } => Some(channel.read().id) == current_channel,
+            Row::Private { id, .. } => Some(*id) == current_channel,
+            _ => false,
+        });
+        if let Some(current) = current {
+            self.cursor = current;
         }
     }
 
+    /// The channel after or before the current one, wrapping around and skipping voice channels
+    pub fn adjacent_channel(&self, current: Option<ChannelId>, forward: bool) -> Option<ChannelId> {
+        let channels = self
+            .rows
+            .iter()
+            .filter_map(|row| match row {
+                Row::Channel { channel, .. } => {
+                    let channel = channel.read();
+                    match channel.kind {
+                        ChannelType::Voice => None,
+                        _ => Some(channel.id),
+                    }
+                }
+                Row::Private { id, .. } => Some(*id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if channels.is_empty() {
+            return None;
+        }
+        let index = match channels.iter().position(|id| Some(*id) == current) {
+            Some(index) if forward => (index + 1) % channels.len(),
+            Some(index) => (index + channels.len() - 1) % channels.len(),
+            None => 0,
+        };
+        Some(channels[index])
+    }
+
+    pub fn key_press(&mut self, key: Key) -> Result<(), Error> {
+        let last = self.rows.len().saturating_sub(1);
+        match key {
+            Key::Up | Key::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            Key::Down | Key::Char('j') => self.cursor = (self.cursor + 1).min(last),
+            Key::PageUp => self.cursor = self.cursor.saturating_sub(PAGE),
+            Key::PageDown => self.cursor = (self.cursor + PAGE).min(last),
+            Key::Home | Key::Char('g') => self.cursor = 0,
+            Key::End | Key::Char('G') => self.cursor = last,
+            Key::Char('\n') => {
+                let id = match self.rows.get(self.cursor) {
+                    Some(Row::Channel { channel, .. }) => {
+                        let channel = channel.read();
+                        if let ChannelType::Voice = channel.kind {
+                            return Ok(());
+                        }
+                        channel.id
+                    }
+                    Some(Row::Private { id, .. }) => *id,
+                    _ => return Ok(()),
+                };
+                self.event_channel.send(Event::SetChannel(id))?;
+                self.focused = false;
+            }
+            Key::Esc | Key::Char('\t') => self.focused = false,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Every text channel in the sidebar, labelled with its guild and category
+    pub fn channel_labels(&self) -> Vec<(String, ChannelId)> {
+        let separator = self.char_set.separator();
+        let mut labels = Vec::new();
+        for guild in &self.guild_list {
+            let guild_name = guild.guild.read().name.clone();
+            let categories = guild
+                .categories
+                .values()
+                .map(|entry| (Some(entry.category.read().name.clone()), &entry.channels));
+            for (category, channels) in categories.chain(Some((None, &guild.misc))) {
+                for channel in channels {
+                    let channel = channel.read();
+                    if let ChannelType::Voice = channel.kind {
+                        continue;
+                    }
+                    let label = match category {
+                        Some(ref category) => format!(
+                            "{} {} {} {} #{}",
+                            guild_name, separator, category, separator, channel.name
+                        ),
+                        None => format!("{} {} #{}", guild_name, separator, channel.name),
+                    };
+                    labels.push((label, channel.id));
+                }
+            }
+        }
+        labels
+    }
+
     pub fn populate_guild_list(&mut self) {
         let user = {
             let cache = CACHE.read();
@@ -123,86 +273,142 @@ impl GuildList {
             }
             guild.misc.sort_by_key(|misc| misc.read().position);
         }
+
+        self.private_channels = utils::private_channels();
+
+        self.build_rows();
     }
 
-    pub fn render(&self, screen: &mut Terminal, size: TermSize, context: &Arc<RwLock<Context>>) {
-        let mut y = 0;
-        let max_y = size.height.saturating_sub(7);
-        let current_channel = context.read().channel;
+    /// Flattens the guild list into the lines shown in the sidebar
+    fn build_rows(&mut self) {
+        self.rows.clear();
         for guild in &self.guild_list {
-            if y >= max_y {
-                break;
-            }
-            screen
-                .buf
-                .string_builder(
-                    LEFT_START,
-                    TOP_START + y,
-                    &truncate(
-                        guild.guild.read().name.clone(),
-                        MAX_LEN.saturating_sub(LEFT_START + 2),
-                    ),
-                )
-                .style(Style::Bold)
-                .draw();
-            y += 1;
+            self.rows.push(Row::Guild(guild.guild.clone()));
 
             let mut categories = guild.categories.values().collect::<Vec<_>>();
             categories.sort_by_key(|entry| entry.category.read().position);
 
             for category in &categories {
-                if y >= max_y {
-                    break;
-                }
-                screen.buf.print(
-                    LEFT_START + 2,
-                    TOP_START + y,
-                    &truncate(
-                        category.category.read().name.clone(),
-                        MAX_LEN.saturating_sub(LEFT_START + 4),
-                    ),
-                );
-                y += 1;
+                self.rows.push(Row::Category(category.category.clone()));
                 for channel in &category.channels {
-                    if y >= max_y {
-                        break;
-                    }
+                    self.rows.push(Row::Channel {
+                        channel: channel.clone(),
+                        indent: 5,
+                    });
+                }
+            }
+            for misc in &guild.misc {
+                self.rows.push(Row::Channel {
+                    channel: misc.clone(),
+                    indent: 2,
+                });
+            }
+        }
+        if !self.private_channels.is_empty() {
+            self.rows.push(Row::DirectMessages);
+            for (id, name, last_message) in &self.private_channels {
+                self.rows.push(Row::Private {
+                    id: *id,
+                    name: name.clone(),
+                    last_message: *last_message,
+                });
+            }
+        }
+        self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Draws the list in `area`, with its border on the rightmost column
+    pub fn render(&self, screen: &mut Terminal, area: Rect, context: &Arc<RwLock<Context>>) {
+        if area.area() == 0 {
+            return;
+        }
+        // Leave a blank row above the list and room for the border
+        let list = Rect::new(
+            area.left() + PADDING,
+            area.top() + 1,
+            area.width.saturating_sub(2 * PADDING),
+            area.height.saturating_sub(2),
+        );
+        let max_y = list.height;
+        let context = context.read();
+        let current_channel = context.channel;
+
+        // Scroll the list so the cursor stays visible
+        let start = if self.focused && self.cursor >= max_y {
+            self.cursor + 1 - max_y
+        } else {
+            0
+        };
+
+        for (y, (index, row)) in self.rows.iter().enumerate().skip(start).take(max_y).enumerate() {
+            let (indent, name, channel) = match row {
+                Row::Guild(guild) => (0, guild.read().name.clone(), None),
+                Row::Category(category) => (2, category.read().name.clone(), None),
+                Row::Channel { channel, indent } => {
                     let channel = channel.read();
-                    let mut text =
-                        truncate(channel.name.clone(), MAX_LEN.saturating_sub(LEFT_START + 7));
-                    if let ChannelType::Voice = channel.kind {
-                        text = format!("{} {}", context.read().char_set.volume_off(), text);
-                    }
-                    if Some(channel.id) == current_channel {
-                        screen
-                            .buf
-                            .string_builder(LEFT_START + 5, TOP_START + y, &text)
-                            .style(Style::Bold)
-                            .draw();
+                    let name = if let ChannelType::Voice = channel.kind {
+                        format!("{} {}", self.char_set.volume_off(), channel.name)
                     } else {
-                        screen.buf.print(LEFT_START + 5, TOP_START + y, &text);
+                        channel.name.clone()
+                    };
+                    (*indent, name, Some((channel.id, channel.last_message_id)))
+                }
+                Row::DirectMessages => (0, "Direct Messages".to_owned(), None),
+                Row::Private {
+                    id,
+                    name,
+                    last_message,
+                } => (2, name.clone(), Some((*id, *last_message))),
+            };
+
+            let theme = &context.theme;
+            let (mut look, mentions) = match channel {
+                Some((id, last_message)) => {
+                    let mut look = Look::default();
+                    if context.unread.is_unread(id, last_message) {
+                        look = look.merge(theme.unread);
+                    }
+                    if Some(id) == current_channel {
+                        look = look.merge(theme.selected_channel);
                     }
-                    y += 1;
+                    (look, context.unread.activity(id).mentions)
                 }
+                // Guild and section headings
+                None if indent == 0 => (theme.guild, 0),
+                None => (Look::default(), 0),
+            };
+            if self.focused && index == self.cursor {
+                look = look.merge(theme.cursor);
             }
-            for misc in &guild.misc {
-                if y >= max_y {
-                    break;
-                }
-                screen.buf.print(
-                    LEFT_START + 2,
-                    TOP_START + y,
-                    &truncate(
-                        misc.read().name.clone(),
-                        MAX_LEN.saturating_sub(LEFT_START + 4),
-                    ),
+            let badge = if mentions > 0 {
+                format!(" {}", mentions)
+            } else {
+                String::new()
+            };
+            let text = truncate(
+                name,
+                list.width.saturating_sub(indent + badge.width()),
+                self.char_set.ellipsis(),
+            );
+
+            let x = list.left() + indent;
+            screen.draw_styled(x, list.top() + y, &text, look);
+            if !badge.is_empty() {
+                screen.draw_styled(
+                    x + text.width(),
+                    list.top() + y,
+                    &badge,
+                    context.theme.mention,
                 );
-                y += 1;
             }
         }
 
-        screen
-            .buf
-            .draw_vertical_line(MAX_LEN - 1, 1, size.height.saturating_sub(6))
+        screen.draw_vertical_line(
+            area.right() - 1,
+            area.top(),
+            area.height,
+            self.char_set.box_chars()[5],
+            context.theme.border,
+        )
     }
 }
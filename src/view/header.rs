@@ -0,0 +1,44 @@
+use discord::utils;
+use model::{Context, Rect};
+use view::terminal::Terminal;
+
+use std::sync::Arc;
+
+use serenity::model::channel::Channel;
+use serenity::prelude::RwLock;
+
+/// Draws the open channel's name and topic above the messages
+pub fn render(screen: &mut Terminal, area: Rect, context: &Arc<RwLock<Context>>) {
+    let context = context.read();
+    let channel_id = match context.channel {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+
+    let name: String = utils::channel_name(channel_id)
+        .chars()
+        .take(area.width)
+        .collect();
+    let name_len = name.chars().count();
+    screen.draw_styled(area.left(), area.top(), &name, context.theme.guild);
+
+    let topic = match channel_id.find() {
+        Some(Channel::Guild(channel)) => channel.read().topic.clone(),
+        _ => None,
+    };
+    if let Some(topic) = topic {
+        let topic: String = topic
+            .lines()
+            .next()
+            .unwrap_or("")
+            .chars()
+            .take(area.width.saturating_sub(name_len + 1))
+            .collect();
+        screen.draw_styled(
+            area.left() + name_len + 1,
+            area.top(),
+            &topic,
+            context.theme.notice,
+        );
+    }
+}
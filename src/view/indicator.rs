@@ -8,15 +8,11 @@ use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use model::Event;
+use model::{Event, Rect};
 
 use serenity::model::event::TypingStartEvent;
 use serenity::model::id::UserId;
 use serenity::model::user::User;
-use termbuf::TermSize;
-
-const BOTTOM_START: usize = 1;
-const SIDE_PADDING: usize = 3;
 
 pub struct Indicator {
     events: Arc<Mutex<Vec<TypingStartEvent>>>,
@@ -81,7 +77,8 @@ impl Indicator {
         });
     }
 
-    pub fn render(&self, screen: &mut Terminal, size: TermSize) {
+    /// Draws the names of typing users against the right of `area`
+    pub fn render(&self, screen: &mut Terminal, area: Rect) {
         let text = self
             .events
             .lock()
@@ -89,9 +86,10 @@ impl Indicator {
             .filter_map(|e| self.fetch_user_name(e.user_id))
             .collect::<Vec<_>>()
             .join(", ");
+        let text: String = text.chars().take(area.width).collect();
         screen.buf.print(
-            size.width.saturating_sub(SIDE_PADDING + 1 + text.len()),
-            size.height - BOTTOM_START,
+            area.right().saturating_sub(text.chars().count()),
+            area.top(),
             &text,
         );
     }
@@ -1,117 +1,408 @@
-use model::Event;
+use model::{Action, Context, Event, History, Keymap, Rect, Scroll};
 use view::terminal::Terminal;
 
+use std::cell::Cell;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
+use serenity::prelude::RwLock;
 use termbuf::termion::event::Key;
-use termbuf::TermSize;
 
 use failure::Error;
 
-const BOTTOM_START: usize = 3;
-const SIDE_PADDING: usize = 3;
+use command::CommandHandler;
+use helpers::editor::{self, LineEditor};
+use view::completion::{self, Candidate};
 
 enum State {
     Message,
     Command,
+    /// Editing one of my sent messages
+    Edit(ChannelId, MessageId),
+}
+
+/// A walk back through the history, started by pressing Up
+struct Recall {
+    /// Whether commands or the current channel's messages are being recalled
+    commands: bool,
+    /// Entries back from the newest
+    index: usize,
+    /// The line that was being composed when the recall started
+    draft: String,
+}
+
+/// Candidates cycled through by repeated Tab presses
+struct Completion {
+    /// Byte offset of the word being completed
+    start: usize,
+    candidates: Vec<Candidate>,
+    index: usize,
 }
 
 pub struct Input {
-    text: String,
+    editor: LineEditor,
+    /// Columns scrolled off the left edge to keep the cursor visible
+    offset: Cell<usize>,
+    /// First line shown when the text has more lines than the box
+    top: Cell<usize>,
+    /// Rows the box may grow to
+    max_rows: usize,
     state: State,
     typing: bool,
+    history: History,
+    recall: Option<Recall>,
+    completion: Option<Completion>,
+    /// Completed text and the mention it is sent as
+    replacements: Vec<(String, String)>,
+    /// Channel whose message history is recalled
+    channel: Option<ChannelId>,
+    keymap: Keymap,
     event_channel: Sender<Event>,
 }
 
 impl Input {
-    pub fn new(event_channel: Sender<Event>) -> Input {
+    pub fn new(
+        event_channel: Sender<Event>,
+        max_rows: usize,
+        history: History,
+        channel: Option<ChannelId>,
+        keymap: Keymap,
+    ) -> Input {
         Input {
-            text: String::new(),
+            editor: LineEditor::new(),
+            offset: Cell::new(0),
+            top: Cell::new(0),
+            max_rows,
             state: State::Message,
             typing: true,
+            history,
+            recall: None,
+            completion: None,
+            replacements: Vec::new(),
+            channel,
+            keymap,
             event_channel,
         }
     }
 
-    pub fn render(&self, screen: &mut Terminal, size: TermSize) {
-        let saturated_side = (size.width as usize).saturating_sub(SIDE_PADDING * 2);
-        let clipped_text = if self.text.len() > saturated_side {
-            let clip = self
-                .text
-                .chars()
-                .skip(
-                    self.text
-                        .len()
-                        .saturating_sub(saturated_side.saturating_sub(1)),
-                )
-                .collect::<String>();
-
-            "…".to_owned() + &clip
+    pub fn set_channel(&mut self, channel: Option<ChannelId>) {
+        self.channel = channel;
+        self.recall = None;
+    }
+
+    pub fn save_history(&self) -> Result<(), Error> {
+        self.history.save()
+    }
+
+    /// Completes the word before the cursor, or moves on to the next candidate
+    ///
+    /// Returns false if there is nothing to complete
+    pub fn complete(&mut self, guild_id: Option<GuildId>, commands: &CommandHandler) -> bool {
+        if let Some(ref mut completion) = self.completion {
+            completion.index = (completion.index + 1) % completion.candidates.len();
+            self.editor.replace_before_cursor(
+                completion.start,
+                &completion.candidates[completion.index].text,
+            );
+            return true;
+        }
+
+        let word = completion::word_at(self.editor.text(), self.editor.cursor());
+        let (start, candidates) = match word {
+            Some(word) => (word.start, completion::candidates(&word, guild_id, commands)),
+            None => return false,
+        };
+        if candidates.is_empty() {
+            return false;
+        }
+        self.editor.replace_before_cursor(start, &candidates[0].text);
+        self.completion = Some(Completion {
+            start,
+            candidates,
+            index: 0,
+        });
+        true
+    }
+
+    /// Starts a reply quoting a message and mentioning its author
+    pub fn reply(&mut self, content: &str, author_nick: &str, author_id: UserId) {
+        let mention = format!("@{}", author_nick);
+        let mut text: String = content.lines().map(|line| format!("> {}\n", line)).collect();
+        text += &mention;
+        text.push(' ');
+
+        self.state = State::Message;
+        self.editor.set_text(text);
+        self.replacements.push((mention, format!("<@{}>", author_id.0)));
+    }
+
+    /// Loads a sent message into the input, replacing it when submitted
+    pub fn edit(&mut self, channel_id: ChannelId, message_id: MessageId, content: String) {
+        self.state = State::Edit(channel_id, message_id);
+        self.editor.set_text(content);
+    }
+
+    /// Replaces completed names with the mentions they stand for
+    fn apply_replacements(&mut self, mut text: String) -> String {
+        // Longest first, so a completed name is never replaced by a shorter prefix of it
+        self.replacements.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        for (completed, mention) in self.replacements.drain(..) {
+            text = text.replace(&completed, &mention);
+        }
+        text
+    }
+
+    /// Accepts the candidate being shown, remembering the mention it stands for
+    fn finish_completion(&mut self) {
+        if let Some(completion) = self.completion.take() {
+            let candidate = &completion.candidates[completion.index];
+            if let Some(ref replacement) = candidate.replacement {
+                self.replacements
+                    .push((candidate.text.clone(), replacement.clone()));
+            }
+        }
+    }
+
+    fn ring(&self, commands: bool) -> &[String] {
+        if commands {
+            self.history.commands()
         } else {
-            self.text.clone()
+            self.channel
+                .map_or(&[], |channel| self.history.messages(channel))
+        }
+    }
+
+    /// Shows a history entry, or the saved draft
+    fn show(&mut self, text: String) {
+        if text.starts_with(':') {
+            self.state = State::Command;
+        }
+        self.editor.set_text(text);
+    }
+
+    fn recall_older(&mut self) {
+        let (commands, index) = match self.recall {
+            Some(ref recall) => (recall.commands, recall.index + 1),
+            None => (self.editor.text().starts_with(':'), 0),
+        };
+        let entry = {
+            let ring = self.ring(commands);
+            if index >= ring.len() {
+                return;
+            }
+            ring[ring.len() - 1 - index].clone()
         };
 
-        screen.buf.draw_box(
-            SIDE_PADDING - 1,
-            size.height.saturating_sub(BOTTOM_START + 1),
-            saturated_side,
-            1,
-        );
+        let draft = match self.recall.take() {
+            Some(recall) => recall.draft,
+            None => self.editor.text().to_owned(),
+        };
+        self.recall = Some(Recall {
+            commands,
+            index,
+            draft,
+        });
+        self.show(if commands {
+            format!(":{}", entry)
+        } else {
+            entry
+        });
+    }
 
-        screen.buf.print(
-            SIDE_PADDING,
-            size.height.saturating_sub(BOTTOM_START),
-            &clipped_text,
-        );
+    fn recall_newer(&mut self) {
+        let recall = match self.recall.take() {
+            Some(recall) => recall,
+            None => return,
+        };
+        // Stepping past the newest entry brings back the draft
+        if recall.index == 0 {
+            self.show(recall.draft);
+            return;
+        }
+
+        let index = recall.index - 1;
+        let entry = {
+            let ring = self.ring(recall.commands);
+            ring[ring.len() - 1 - index].clone()
+        };
+        let commands = recall.commands;
+        self.recall = Some(Recall { index, ..recall });
+        self.show(if commands {
+            format!(":{}", entry)
+        } else {
+            entry
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.editor.is_empty()
+    }
+
+    fn editing(&self) -> bool {
+        match self.state {
+            State::Edit(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Height of the text area, growing with the number of lines being composed
+    pub fn rows(&self) -> usize {
+        self.editor.lines().len().min(self.max_rows)
+    }
+
+    /// Draws the input box with its outline on the edges of `area`
+    pub fn render(&self, screen: &mut Terminal, area: Rect, context: &Arc<RwLock<Context>>) {
+        let text = area.inner(1);
+        if text.area() == 0 {
+            return;
+        }
+        let rows = text.height;
+        let offset = self.editor.scroll_offset(self.offset.get(), text.width);
+        self.offset.set(offset);
+
+        // Scroll vertically so the cursor line stays inside the box
+        let cursor_line = self.editor.cursor_line();
+        let top = self
+            .top
+            .get()
+            .min(cursor_line)
+            .max((cursor_line + 1).saturating_sub(rows));
+        self.top.set(top);
+
+        {
+            let context = context.read();
+            screen.draw_box(
+                area.left(),
+                area.top(),
+                text.width,
+                text.height,
+                context.char_set.box_chars(),
+                context.theme.border,
+            );
+        }
+
+        for (row, line) in self.editor.lines().iter().skip(top).take(rows).enumerate() {
+            screen.buf.print(
+                text.left(),
+                text.top() + row,
+                &editor::visible(line, offset, text.width),
+            );
+        }
 
         screen.buf.set_cursor_position(
-            SIDE_PADDING + clipped_text.len() + 1,
-            size.height.saturating_sub(BOTTOM_START) + 1,
+            text.left() + self.editor.cursor_column() - offset + 1,
+            text.top() + cursor_line - top + 1,
         );
     }
 
     pub fn submit(&mut self) -> Result<(), Error> {
+        self.finish_completion();
+        let text = self.editor.take();
         let event = match self.state {
-            State::Message => Event::UserMessage(self.text.clone()),
-            State::Command => Event::UserCommand(self.text[1..].to_owned()),
+            State::Message => {
+                if let Some(channel) = self.channel {
+                    self.history.push_message(channel, text.clone());
+                }
+                Event::UserMessage(self.apply_replacements(text))
+            }
+            State::Command => {
+                let command = text[1..].to_owned();
+                self.history.push_command(command.clone());
+                Event::UserCommand(command)
+            }
+            State::Edit(channel_id, message_id) => {
+                Event::EditMessage(channel_id, message_id, self.apply_replacements(text))
+            }
         };
         self.state = State::Message;
+        self.recall = None;
+        self.replacements.clear();
         self.event_channel.send(event)?;
-        self.text.clear();
         Ok(())
     }
 
     pub fn key_press(&mut self, key: Key) -> Result<(), Error> {
-        match key {
-            Key::Backspace | Key::Delete => {
-                if self.text.len() == 1 {
-                    self.state = State::Message
+        self.finish_completion();
+        match self.keymap.action(key) {
+            Some(action) => self.perform(action)?,
+            None => if let Key::Char(ch) = key {
+                // Unbound control characters, such as Tab set to "none", are not typed
+                if ch.is_control() {
+                    return Ok(());
                 }
-                let _ = self.text.pop();
-            }
-            Key::Esc => self.state = State::Message,
-            Key::Char('\n') => {
-                self.submit()?;
-            }
-            Key::Char('\t') => {
-                // Glitching out, to be used for channel switching
-            }
-            Key::Char(ch) => {
-                if ch == ':' && self.text.is_empty() {
+                if ch == ':' && self.editor.is_empty() && !self.editing() {
                     self.state = State::Command
                 }
                 if self.typing {
-                    if self.text.is_empty() {
+                    if self.editor.is_empty() {
                         self.typing = false;
                     }
                 } else {
                     self.event_channel.send(Event::UserTyping).unwrap();
                     self.typing = true;
                 }
-                self.text.push(ch);
+                self.editor.insert(ch);
+            },
+        }
+        // Editing away the leading ':' turns a command back into a message
+        if let State::Command = self.state {
+            if !self.editor.text().starts_with(':') {
+                self.state = State::Message;
+                self.event_channel.send(Event::CommandCancelled)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn perform(&mut self, action: Action) -> Result<(), Error> {
+        match action {
+            Action::Backspace => self.editor.backspace(),
+            Action::Delete => self.editor.delete(),
+            Action::Cancel => {
+                match self.state {
+                    // Abandoning an edit also drops the loaded message
+                    State::Edit(..) => self.editor.clear(),
+                    State::Command => self.event_channel.send(Event::CommandCancelled)?,
+                    State::Message => {}
+                }
+                self.state = State::Message
+            }
+            Action::Submit => self.submit()?,
+            Action::Newline => self.editor.newline(),
+            Action::Complete | Action::FocusSidebar => {
+                self.event_channel.send(Event::FocusSidebar)?
+            }
+            Action::SelectMessages => self.event_channel.send(Event::SelectMessages)?,
+            Action::LineUp => if !self.editor.line_up() {
+                self.recall_older();
+            },
+            Action::LineDown => if !self.editor.line_down() {
+                self.recall_newer();
+            },
+            Action::Left => self.editor.left(),
+            Action::Right => self.editor.right(),
+            Action::WordLeft => self.editor.word_left(),
+            Action::WordRight => self.editor.word_right(),
+            // Line start and end move the cursor while composing, and scroll the messages otherwise
+            Action::LineStart if !self.editor.is_empty() => self.editor.home(),
+            Action::LineEnd if !self.editor.is_empty() => self.editor.end(),
+            Action::LineStart | Action::ScrollTop => {
+                self.event_channel.send(Event::Scroll(Scroll::Top))?
+            }
+            Action::LineEnd | Action::ScrollBottom => {
+                self.event_channel.send(Event::Scroll(Scroll::Bottom))?
             }
-            Key::Ctrl('u') => self.text.clear(),
-            _ => {}
+            Action::KillWordBack => self.editor.kill_word_back(),
+            // The channel switcher only opens with an empty input, so its key kills while composing
+            Action::KillToEnd | Action::SwitchChannel => self.editor.kill_to_end(),
+            Action::KillToStart => self.editor.kill_to_start(),
+            Action::ClearLine => self.editor.clear(),
+            Action::Yank => self.editor.yank(),
+            Action::YankPop => self.editor.yank_pop(),
+            Action::ScrollPageUp => self.event_channel.send(Event::Scroll(Scroll::PageUp))?,
+            Action::ScrollPageDown => self.event_channel.send(Event::Scroll(Scroll::PageDown))?,
+            Action::Quit | Action::ToggleSidebar | Action::NextChannel | Action::PrevChannel => {}
         }
         Ok(())
     }
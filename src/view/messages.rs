@@ -1,37 +1,210 @@
-use serenity::model::channel;
+use chrono::{DateTime, Local, TimeZone};
+use serenity::model::channel::{self, Embed, ReactionType};
 use serenity::model::event::MessageUpdateEvent;
-use serenity::model::id::{ChannelId, MessageId, UserId};
-use serenity::prelude::Mutex;
+use serenity::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
 use serenity::prelude::RwLock;
 use serenity::utils::Colour;
+use serenity::CACHE;
+use termbuf::termion::event::Key;
 use termbuf::Color;
-use termbuf::Style;
-use termbuf::TermSize;
-use textwrap::fill;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::env;
 use std::io;
+use std::mem;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::thread;
+
+use failure::Error;
 
 use discord::utils;
-use model::{Application, Context, MessageItem};
+use helpers::chars::CharSet;
+use helpers::lru::Lru;
+use helpers::size::human_size;
+use helpers::system;
+use model::{Application, Context, Event, MessageItem, Rect, Scroll, Theme};
+use view::spans::{self, Attributes, Resolver, Span};
 use view::terminal::Terminal;
 
 const LEFT_PADDING: usize = 20;
 const RIGHT_PADDING: usize = 5;
 const TIME_PADDING: usize = 3;
-const LEFT_START: usize = 5;
-const LEFT_START_EXTENDED: usize = 30;
-const TOP_START: usize = 1;
-const BOTTOM_DIFF: usize = 6;
-
-fn color_to_8bit(colour: ::serenity::utils::Colour) -> Color {
-    let r = (u16::from(colour.r()) * 5 / 255) as u8;
-    let g = (u16::from(colour.g()) * 5 / 255) as u8;
-    let b = (u16::from(colour.b()) * 5 / 255) as u8;
-    Color::AnsiValue(16 + 36 * r + 6 * g + b)
+/// Columns notices are indented by, leaving the timestamps hanging to their left
+const NOTICE_INDENT: usize = 2;
+/// Number of older messages fetched each time the top of the history is reached
+const HISTORY_CHUNK: u64 = 50;
+/// Number of recently shown channels kept in memory
+const MAX_BUFFERS: usize = 10;
+const EMBED_INDENT: &str = "  ";
+
+/// Resolves mentions in a message from the cache
+struct MentionResolver<'a> {
+    messages: &'a Messages,
+    guild_id: Option<GuildId>,
+}
+
+impl<'a> Resolver for MentionResolver<'a> {
+    fn user(&self, user_id: UserId) -> String {
+        self.messages
+            .lookup_nick(self.guild_id, user_id)
+            .map(|(nick, _)| format!("@{}", nick))
+            .unwrap_or_else(|| "@unknown-user".to_owned())
+    }
+
+    fn channel(&self, channel_id: ChannelId) -> String {
+        utils::channel_name(channel_id)
+    }
+
+    fn role(&self, role_id: RoleId) -> (String, Option<Colour>) {
+        match role_id.find() {
+            Some(role) => {
+                // Roles without a colour have it set to 0
+                let colour = if role.colour.0 == 0 {
+                    None
+                } else {
+                    Some(role.colour)
+                };
+                (format!("@{}", role.name), colour)
+            }
+            None => ("@deleted-role".to_owned(), None),
+        }
+    }
+
+    fn timestamp(&self, seconds: i64, style: Option<char>) -> String {
+        let time = match Local.timestamp_opt(seconds, 0).single() {
+            Some(time) => time,
+            None => return "<invalid date>".to_owned(),
+        };
+        let fmt = &self.messages.timestamp_fmt;
+        match style {
+            Some('R') => relative_time(time),
+            Some('d') | Some('D') => time.format("%Y-%m-%d").to_string(),
+            Some('f') | Some('F') => time.format(&format!("%Y-%m-%d {}", fmt)).to_string(),
+            _ => time.format(fmt).to_string(),
+        }
+    }
+}
+
+/// The reactions line shown under a message, with my own reactions in bold
+fn reaction_spans(msg: &channel::Message) -> Vec<Span> {
+    let mut spans = Vec::new();
+    for reaction in &msg.reactions {
+        if !spans.is_empty() {
+            spans.push(Span::plain("  ".to_owned()));
+        }
+        let emoji = match reaction.reaction_type {
+            ReactionType::Custom { ref name, .. } => {
+                format!(":{}:", name.as_ref().map_or("emoji", |name| name.as_str()))
+            }
+            ReactionType::Unicode(ref emoji) => emoji.clone(),
+        };
+        let attrs = Attributes {
+            bold: reaction.me,
+            ..Attributes::default()
+        };
+        spans.push(Span::new(format!("{} {}", emoji, reaction.count), attrs));
+    }
+    spans
+}
+
+/// Lays out an embed as a block of lines behind a bar in the embed's colour
+fn embed_lines(
+    embed: &Embed,
+    resolver: &Resolver,
+    char_set: &CharSet,
+    width: usize,
+) -> Vec<Vec<Span>> {
+    let mut body = Vec::new();
+    let bold = Attributes {
+        bold: true,
+        ..Attributes::default()
+    };
+    let link = Attributes {
+        link: true,
+        ..Attributes::default()
+    };
+
+    if let Some(ref author) = embed.author {
+        body.push(Span::new(format!("{}\n", author.name), bold));
+    }
+    if let Some(ref title) = embed.title {
+        body.push(Span::new(format!("{}\n", title), bold));
+    }
+    if let Some(ref description) = embed.description {
+        body.extend(spans::from_markdown(description, resolver, char_set.quote_bar()));
+        body.push(Span::plain("\n".to_owned()));
+    }
+    for field in &embed.fields {
+        body.push(Span::new(format!("{}\n", field.name), bold));
+        body.extend(spans::from_markdown(&field.value, resolver, char_set.quote_bar()));
+        body.push(Span::plain("\n".to_owned()));
+    }
+    if let Some(ref image) = embed.image {
+        body.push(Span::new(format!("{}\n", image.url), link));
+    }
+    if let Some(ref footer) = embed.footer {
+        let faint = Attributes {
+            faint: true,
+            ..Attributes::default()
+        };
+        body.push(Span::new(footer.text.clone(), faint));
+    }
+    // Link previews with nothing else to show are reduced to their URL
+    if body.is_empty() {
+        if let Some(ref url) = embed.url {
+            body.push(Span::new(url.clone(), link));
+        }
+    }
+
+    let bar = Attributes {
+        // Embeds without a colour have it set to 0
+        colour: if embed.colour.0 == 0 {
+            None
+        } else {
+            Some(embed.colour)
+        },
+        ..Attributes::default()
+    };
+    let mut lines = spans::wrap(
+        &body,
+        width.saturating_sub(EMBED_INDENT.len() + char_set.embed_bar().chars().count()),
+    );
+    // Drop the empty line left by a trailing line break
+    if lines.len() > 1 && lines.last().map_or(false, |line| line.is_empty()) {
+        lines.pop();
+    }
+    for line in &mut lines {
+        line.insert(0, Span::new(char_set.embed_bar().to_owned(), bar));
+        line.insert(0, Span::plain(EMBED_INDENT.to_owned()));
+    }
+    lines
+}
+
+/// Formats a time as "in 5 minutes" or "3 days ago"
+fn relative_time(time: DateTime<Local>) -> String {
+    let seconds = time.signed_duration_since(Local::now()).num_seconds();
+    let (amount, unit) = match seconds.abs() {
+        s if s < 60 => (s, "second"),
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 60 * 60 * 24 => (s / (60 * 60), "hour"),
+        s if s < 60 * 60 * 24 * 30 => (s / (60 * 60 * 24), "day"),
+        s if s < 60 * 60 * 24 * 365 => (s / (60 * 60 * 24 * 30), "month"),
+        s => (s / (60 * 60 * 24 * 365), "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    if seconds < 0 {
+        format!("{} {}{} ago", amount, unit, plural)
+    } else {
+        format!("in {} {}{}", amount, unit, plural)
+    }
+}
+
+/// Messages of a channel that is not shown, kept up to date in the background
+struct Buffer {
+    messages: Vec<MessageItem>,
+    history_exhausted: bool,
 }
 
 pub struct Messages {
@@ -40,11 +213,34 @@ pub struct Messages {
     timestamp_fmt: String,
     truecolor: bool,
     nickname_cache: RefCell<HashMap<UserId, (String, Option<Colour>)>>,
-    show_sidebar: Arc<Mutex<bool>>,
+    char_set: CharSet,
+    theme: Theme,
+    /// Lines scrolled up from the newest message
+    scroll: Cell<usize>,
+    /// Area of the last render, used to wrap messages outside of rendering
+    area: Cell<Option<Rect>>,
+    /// Channel the loaded messages belong to
+    channel: Cell<Option<ChannelId>>,
+    loading_history: Cell<bool>,
+    history_exhausted: Cell<bool>,
+    /// Recently shown channels, so switching back does not refetch them
+    buffers: RefCell<Lru<ChannelId, Buffer>>,
+    /// Message highlighted in selection mode, kept while typing a command that acts on it
+    selected: Cell<Option<MessageId>>,
+    /// Whether keys go to the selection instead of the input box
+    selecting: Cell<bool>,
+    /// Whether the selected message is deleted if `d` is pressed again
+    pending_delete: Cell<bool>,
+    event_channel: Sender<Event>,
 }
 
 impl Messages {
-    pub fn new(timestamp_fmt: String, show_sidebar: bool) -> Messages {
+    pub fn new(
+        timestamp_fmt: String,
+        char_set: CharSet,
+        theme: Theme,
+        event_channel: Sender<Event>,
+    ) -> Messages {
         let truecolor = match env::var("COLORTERM") {
             Ok(term) => term.to_lowercase() == "truecolor",
             Err(_) => false,
@@ -56,38 +252,59 @@ impl Messages {
             timestamp_fmt,
             truecolor,
             nickname_cache: RefCell::new(HashMap::new()),
-            show_sidebar: Arc::new(Mutex::new(show_sidebar)),
+            char_set,
+            theme,
+            scroll: Cell::new(0),
+            area: Cell::new(None),
+            channel: Cell::new(None),
+            loading_history: Cell::new(false),
+            history_exhausted: Cell::new(false),
+            buffers: RefCell::new(Lru::new(MAX_BUFFERS)),
+            selected: Cell::new(None),
+            selecting: Cell::new(false),
+            pending_delete: Cell::new(false),
+            event_channel,
         }
     }
 
-    pub fn set_show_sidebar(&self, state: bool) {
-        *self.show_sidebar.lock() = state
+    pub fn add_msg(&self, msg: MessageItem) {
+        // Keep the viewport still while scrolled up
+        if self.scroll.get() > 0 {
+            if let Some(area) = self.area.get() {
+                let lines = self.item_lines(&msg, area).len();
+                self.scroll.set(self.scroll.get() + lines);
+            }
+        }
+        self.messages.borrow_mut().push(msg);
     }
 
-    pub fn showing_sidebar(&self) -> bool {
-        *self.show_sidebar.lock()
+    /// Applies a change to the messages of a channel, whether shown or buffered
+    fn with_channel<F: FnOnce(&mut Vec<MessageItem>)>(&self, channel_id: ChannelId, f: F) {
+        if Some(channel_id) == self.channel.get() {
+            f(&mut self.messages.borrow_mut());
+        } else if let Some(buffer) = self.buffers.borrow_mut().get_mut(&channel_id) {
+            f(&mut buffer.messages);
+        }
     }
 
-    pub fn add_msg(&self, msg: MessageItem) {
-        self.messages.borrow_mut().push(msg);
+    /// Adds a new Discord message if its channel is shown or buffered
+    pub fn add_message(&self, msg: Box<channel::Message>) {
+        let channel_id = msg.channel_id;
+        if Some(channel_id) == self.channel.get() {
+            self.add_msg(MessageItem::DiscordMessage(msg));
+        } else if let Some(buffer) = self.buffers.borrow_mut().get_mut(&channel_id) {
+            buffer.messages.push(MessageItem::DiscordMessage(msg));
+        }
     }
 
     pub fn delete_msg(&self, channel_id: ChannelId, message_id: MessageId) {
-        let mut msg_index = None;
-        for (i, msg) in self.messages.borrow().iter().enumerate() {
-            match msg {
-                MessageItem::DiscordMessage(msg) => {
-                    debug!("Deleting message: {}", message_id);
-                    if msg.channel_id == channel_id && msg.id == message_id {
-                        msg_index = Some(i);
-                        break;
-                    }
-                }
-            }
-        }
-        if let Some(index) = msg_index {
-            self.messages.borrow_mut().remove(index);
-        }
+        debug!("Deleting message: {}", message_id);
+        self.with_channel(channel_id, |messages| {
+            messages.retain(|msg| match msg {
+                MessageItem::DiscordMessage(msg) => msg.id != message_id,
+                MessageItem::Notice(_) => true,
+            })
+        });
     }
 
     pub fn delete_msg_bulk(&self, channel_id: ChannelId, message_ids: &[MessageId]) {
@@ -99,25 +316,91 @@ impl Messages {
                 .collect::<Vec<_>>()
                 .join(", ")
         );
-        self.messages.borrow_mut().retain(|msg| match msg {
-            MessageItem::DiscordMessage(msg) => {
-                msg.channel_id != channel_id && !message_ids.contains(&msg.id)
-            }
+        self.with_channel(channel_id, |messages| {
+            messages.retain(|msg| match msg {
+                MessageItem::DiscordMessage(msg) => !message_ids.contains(&msg.id),
+                MessageItem::Notice(_) => true,
+            })
         });
     }
 
     pub fn update_message(&self, update: MessageUpdateEvent) {
-        for mut msg in self.messages.borrow_mut().iter_mut() {
-            match msg {
-                MessageItem::DiscordMessage(ref mut msg) => {
-                    if update.id == msg.id && update.channel_id == msg.channel_id {
+        self.with_channel(update.channel_id, |messages| {
+            for mut msg in messages.iter_mut() {
+                match msg {
+                    MessageItem::DiscordMessage(ref mut msg) => if update.id == msg.id {
                         debug!("Updated message: {}", msg.id);
                         utils::update_msg(msg, update);
                         break;
-                    }
+                    },
+                    MessageItem::Notice(_) => {}
                 }
             }
-        }
+        });
+    }
+
+    /// Applies a change to a loaded message
+    fn with_message<F: FnOnce(&mut channel::Message)>(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        f: F,
+    ) {
+        self.with_channel(channel_id, |messages| {
+            let msg = messages
+                .iter_mut()
+                .filter_map(|item| match item {
+                    MessageItem::DiscordMessage(msg) if msg.id == message_id => Some(msg),
+                    _ => None,
+                })
+                .next();
+            if let Some(msg) = msg {
+                f(msg);
+            }
+        });
+    }
+
+    pub fn add_reaction(&self, reaction: &channel::Reaction) {
+        let me = reaction.user_id == CACHE.read().user.id;
+        self.with_message(reaction.channel_id, reaction.message_id, |msg| {
+            utils::add_reaction(msg, &reaction.emoji, me)
+        });
+    }
+
+    pub fn remove_reaction(&self, reaction: &channel::Reaction) {
+        let me = reaction.user_id == CACHE.read().user.id;
+        self.with_message(reaction.channel_id, reaction.message_id, |msg| {
+            utils::remove_reaction(msg, &reaction.emoji, me)
+        });
+    }
+
+    pub fn remove_all_reactions(&self, channel_id: ChannelId, message_id: MessageId) {
+        self.with_message(channel_id, message_id, |msg| msg.reactions.clear());
+    }
+
+    /// Returns a copy of the loaded Discord messages, oldest first
+    pub fn discord_messages(&self) -> Vec<channel::Message> {
+        self.messages
+            .borrow()
+            .iter()
+            .filter_map(|msg| match msg {
+                MessageItem::DiscordMessage(msg) => Some((**msg).clone()),
+                MessageItem::Notice(_) => None,
+            })
+            .collect()
+    }
+
+    /// Channel and id of the newest loaded Discord message
+    pub fn last_message(&self) -> Option<(ChannelId, MessageId)> {
+        self.messages
+            .borrow()
+            .iter()
+            .rev()
+            .filter_map(|item| match item {
+                MessageItem::DiscordMessage(msg) => Some((msg.channel_id, msg.id)),
+                MessageItem::Notice(_) => None,
+            })
+            .next()
     }
 
     pub fn load_messages(&self, app: &Application) {
@@ -126,160 +409,515 @@ impl Messages {
         let num = app.view.terminal_size.height;
         let retriever = GetMessages::default().limit(num as u64);
         if let Some(channel) = app.context.read().channel {
-            self.messages.borrow_mut().clear();
+            if let Some(current) = self.channel.get() {
+                let buffer = Buffer {
+                    messages: mem::replace(&mut *self.messages.borrow_mut(), Vec::new()),
+                    history_exhausted: self.history_exhausted.get(),
+                };
+                self.buffers.borrow_mut().put(current, buffer);
+            }
+            self.scroll.set(0);
+            self.channel.set(Some(channel));
+            self.loading_history.set(false);
+            self.clear_selection();
 
-            for message in channel
-                .messages(|_| retriever)
-                .unwrap()
-                .iter()
-                .rev()
-                .cloned()
-            {
+            let buffer = self.buffers.borrow_mut().take(&channel);
+            if let Some(buffer) = buffer {
+                *self.messages.borrow_mut() = buffer.messages;
+                self.history_exhausted.set(buffer.history_exhausted);
+                return;
+            }
+
+            let messages = channel.messages(|_| retriever).unwrap();
+            self.history_exhausted.set(messages.len() < num);
+            for message in messages.iter().rev().cloned() {
                 self.add_msg(MessageItem::DiscordMessage(Box::new(message)));
             }
         }
     }
 
-    fn put_nick(&self, message: &channel::Message, screen: &mut Terminal, x: usize, y: usize) {
-        let mut cache = self.nickname_cache.borrow_mut();
-        let entry = cache.entry(message.author.id);
-
-        use std::collections::hash_map::Entry::*;
-        let (nick, colour) = match entry {
-            Occupied(o) => o.into_mut(),
-            Vacant(v) => {
-                if let Some(member) = utils::member(&message) {
-                    v.insert((
-                        member
-                            .nick
-                            .clone()
-                            .unwrap_or_else(|| message.author.name.to_owned()),
-                        member.colour(),
-                    ))
-                } else {
-                    v.insert((message.author.name.to_owned(), None))
-                }
-            }
+    /// Looks up a user's nickname in a guild, along with their role colour
+    fn lookup_nick(
+        &self,
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+    ) -> Option<(String, Option<Colour>)> {
+        if let Some(entry) = self.nickname_cache.borrow().get(&user_id) {
+            return Some(entry.clone());
+        }
+
+        let member = guild_id
+            .and_then(|guild_id| guild_id.find())
+            .and_then(|guild| guild.read().members.get(&user_id).cloned());
+        let entry = match member {
+            Some(member) => (
+                member
+                    .nick
+                    .clone()
+                    .unwrap_or_else(|| member.user.read().name.to_owned()),
+                member.colour(),
+            ),
+            None => (user_id.find()?.read().name.to_owned(), None),
         };
+        self.nickname_cache
+            .borrow_mut()
+            .insert(user_id, entry.clone());
+        Some(entry)
+    }
+
+    /// The author's nickname and role colour, falling back to their username
+    fn author_nick(&self, message: &channel::Message) -> (String, Option<Colour>) {
+        match self.lookup_nick(utils::guild_id(message), message.author.id) {
+            Some(entry) => entry,
+            None => {
+                let entry = (message.author.name.to_owned(), None);
+                self.nickname_cache
+                    .borrow_mut()
+                    .insert(message.author.id, entry.clone());
+                entry
+            }
+        }
+    }
+
+    pub fn display_name(&self, message: &channel::Message) -> String {
+        self.author_nick(message).0
+    }
+
+    fn put_nick(
+        &self,
+        message: &channel::Message,
+        screen: &mut Terminal,
+        x: usize,
+        y: usize,
+        background: Option<Color>,
+    ) {
+        let (nick, colour) = self.author_nick(message);
 
         if nick.len() > *self.max_name_len.borrow() {
             *self.max_name_len.borrow_mut() = nick.len();
         }
-        match colour {
-            Some(colour) => {
-                if self.truecolor {
-                    screen
-                        .buf
-                        .string_builder(x, y, nick)
-                        .fg(Color::Rgb(colour.r(), colour.g(), colour.b()))
-                        .draw();
+        let mut look = self.theme.nick;
+        if let Some(colour) = colour {
+            look.fg = Some(spans::terminal_colour(colour, self.truecolor));
+        }
+        look.bg = background.or(look.bg);
+        screen.draw_styled(x, y, &nick, look);
+    }
+
+    /// Number of message lines that fit on screen, leaving the bottom row for the scroll marker
+    fn visible_lines(area: Rect) -> usize {
+        area.height.saturating_sub(1)
+    }
+
+    /// Total number of lines the loaded messages wrap to
+    fn total_lines(&self, area: Rect) -> usize {
+        self.messages
+            .borrow()
+            .iter()
+            .map(|item| self.item_lines(item, area).len())
+            .sum()
+    }
+
+    pub fn scroll(&self, scroll: Scroll) {
+        let area = match self.area.get() {
+            Some(area) => area,
+            None => return,
+        };
+        let visible = Messages::visible_lines(area);
+        // Keep a line of context when paging
+        let page = visible.saturating_sub(1).max(1);
+        let max = self.total_lines(area).saturating_sub(visible);
+
+        let offset = match scroll {
+            Scroll::PageUp => self.scroll.get() + page,
+            Scroll::PageDown => self.scroll.get().saturating_sub(page),
+            Scroll::Top => max,
+            Scroll::Bottom => 0,
+        };
+        self.scroll.set(offset.min(max));
+    }
+
+    pub fn selecting(&self) -> bool {
+        self.selecting.get()
+    }
+
+    /// Enters selection mode on the newest message
+    pub fn select_last(&self) {
+        let last = self.message_ids().last().cloned();
+        self.selected.set(last);
+        self.selecting.set(last.is_some());
+        self.scroll_to_selection();
+    }
+
+    pub fn clear_selection(&self) {
+        self.selected.set(None);
+        self.selecting.set(false);
+    }
+
+    pub fn selected_message(&self) -> Option<channel::Message> {
+        let selected = self.selected.get()?;
+        self.messages
+            .borrow()
+            .iter()
+            .filter_map(|item| match item {
+                MessageItem::DiscordMessage(msg) if msg.id == selected => Some((**msg).clone()),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// Ids of the loaded Discord messages, oldest first
+    fn message_ids(&self) -> Vec<MessageId> {
+        self.messages
+            .borrow()
+            .iter()
+            .filter_map(|item| match item {
+                MessageItem::DiscordMessage(msg) => Some(msg.id),
+                MessageItem::Notice(_) => None,
+            })
+            .collect()
+    }
+
+    /// Moves the selection to the next older or newer message
+    fn move_selection(&self, older: bool) {
+        let ids = self.message_ids();
+        let position = match ids.iter().position(|id| Some(*id) == self.selected.get()) {
+            Some(position) => position,
+            None => return,
+        };
+        let position = if older {
+            position.saturating_sub(1)
+        } else {
+            (position + 1).min(ids.len() - 1)
+        };
+        self.selected.set(Some(ids[position]));
+        self.scroll_to_selection();
+    }
+
+    /// Scrolls just enough for the whole selected message to be visible
+    fn scroll_to_selection(&self) {
+        let area = match self.area.get() {
+            Some(area) => area,
+            None => return,
+        };
+        let messages = self.messages.borrow();
+        let index = messages.iter().position(|item| match item {
+            MessageItem::DiscordMessage(msg) => Some(msg.id) == self.selected.get(),
+            MessageItem::Notice(_) => false,
+        });
+        let index = match index {
+            Some(index) => index,
+            None => return,
+        };
+
+        let below: usize = messages[index + 1..]
+            .iter()
+            .map(|item| self.item_lines(item, area).len())
+            .sum();
+        let height = self.item_lines(&messages[index], area).len();
+        let lowest = (below + height).saturating_sub(Messages::visible_lines(area));
+        self.scroll.set(self.scroll.get().min(below).max(lowest));
+    }
+
+    fn notice(&self, text: &str) -> Result<(), Error> {
+        self.event_channel.send(Event::Notice(text.to_owned()))?;
+        Ok(())
+    }
+
+    /// Handles a key in selection mode
+    pub fn key_press(&self, key: Key) -> Result<(), Error> {
+        let confirm_delete = self.pending_delete.replace(false);
+        let message = match self.selected_message() {
+            Some(message) => message,
+            None => {
+                self.clear_selection();
+                return Ok(());
+            }
+        };
+
+        match key {
+            Key::Char('k') | Key::Up => self.move_selection(true),
+            Key::Char('j') | Key::Down => self.move_selection(false),
+            Key::Esc | Key::Char('q') => self.clear_selection(),
+            // Start a command acting on the selected message, such as :react
+            Key::Char(':') => {
+                self.selecting.set(false);
+                self.event_channel.send(Event::Keypress(key))?;
+            }
+            Key::Char('r') => {
+                self.clear_selection();
+                self.event_channel.send(Event::Reply(Box::new(message)))?;
+            }
+            Key::Char('e') => {
+                if message.is_own() {
+                    self.clear_selection();
+                    self.event_channel
+                        .send(Event::StartEdit(Box::new(message)))?;
                 } else {
-                    screen
-                        .buf
-                        .string_builder(x, y, nick)
-                        .fg(color_to_8bit(*colour))
-                        .draw();
+                    self.notice("Only your own messages can be edited")?;
                 }
             }
-            None => {
-                screen.buf.print(x, y, &nick);
+            Key::Char('d') => {
+                if confirm_delete {
+                    let event_channel = self.event_channel.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = message.channel_id.delete_message(message.id) {
+                            event_channel
+                                .send(Event::Notice(format!("Unable to delete message: {}", err)))
+                                .unwrap();
+                        }
+                    });
+                } else {
+                    self.pending_delete.set(true);
+                    self.notice("Press d again to delete the selected message")?;
+                }
+            }
+            Key::Char('y') => {
+                system::copy(&message.content)?;
+                self.notice("Copied message to the clipboard")?;
+            }
+            Key::Char('o') => {
+                if message.attachments.is_empty() {
+                    self.notice("The selected message has no attachments")?;
+                }
+                for attachment in &message.attachments {
+                    system::open(&attachment.url)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn oldest_message(&self) -> Option<MessageId> {
+        self.messages
+            .borrow()
+            .iter()
+            .filter_map(|item| match item {
+                MessageItem::DiscordMessage(msg) => Some(msg.id),
+                MessageItem::Notice(_) => None,
+            })
+            .next()
+    }
+
+    /// Fetches the messages before the oldest loaded one in the background
+    fn load_history(&self, context: &Arc<RwLock<Context>>) {
+        if self.loading_history.get() || self.history_exhausted.get() {
+            return;
+        }
+        let channel_id = match self.channel.get() {
+            Some(channel_id) => channel_id,
+            None => return,
+        };
+        let oldest = match self.oldest_message() {
+            Some(oldest) => oldest,
+            None => return,
+        };
+
+        debug!("Loading history before {}", oldest);
+        self.loading_history.set(true);
+        let event_channel = context.read().event_channel.clone();
+        thread::spawn(move || {
+            match channel_id.messages(|retriever| retriever.before(oldest).limit(HISTORY_CHUNK)) {
+                Ok(messages) => event_channel
+                    .send(Event::HistoryLoaded(channel_id, messages))
+                    .unwrap(),
+                Err(err) => {
+                    event_channel
+                        .send(Event::InternalError(format_err!(
+                            "Unable to load history: {}",
+                            err
+                        )))
+                        .unwrap();
+                    event_channel
+                        .send(Event::HistoryFailed(channel_id))
+                        .unwrap();
+                }
             }
+        });
+    }
+
+    /// Adds older messages, newest first as returned by Discord, to the top of the buffer
+    ///
+    /// Results for a channel that is no longer shown are dropped, leaving any fetch for the shown
+    /// channel running
+    pub fn add_history(&self, channel_id: ChannelId, messages: Vec<channel::Message>) {
+        if Some(channel_id) != self.channel.get() {
+            return;
+        }
+        self.loading_history.set(false);
+        if messages.is_empty() {
+            self.history_exhausted.set(true);
+            return;
+        }
+        // A fetch started before switching away and back may overlap what is already loaded
+        let oldest = self.oldest_message();
+        let older = messages
+            .into_iter()
+            .rev()
+            .filter(|msg| oldest.map_or(true, |oldest| msg.id < oldest))
+            .map(|msg| MessageItem::DiscordMessage(Box::new(msg)));
+        self.messages.borrow_mut().splice(0..0, older);
+    }
+
+    pub fn history_failed(&self, channel_id: ChannelId) {
+        if Some(channel_id) == self.channel.get() {
+            self.loading_history.set(false);
         }
     }
 
     pub fn render(
         &self,
         screen: &mut Terminal,
-        size: TermSize,
+        area: Rect,
         context: &Arc<RwLock<Context>>,
     ) -> Result<(), io::Error> {
-        self.set_show_sidebar(context.read().guild_sidebar_visible);
+        self.area.set(Some(area));
+        let visible = Messages::visible_lines(area);
+        if visible == 0 {
+            return Ok(());
+        }
 
-        let rough_msg_count = size.height;
-        let mut msgs = self.messages.borrow_mut();
-        let msg_diff = msgs.len().saturating_sub(rough_msg_count as usize);
+        let mut skip = self.scroll.get();
+        let mut y = visible - 1;
+        let mut reached_top = true;
+        'items: for item in self.messages.borrow().iter().rev() {
+            let lines = self.item_lines(item, area);
+            for (i, line) in lines.iter().enumerate().rev() {
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                self.draw_line(item, i, line, area, y, screen);
+                if y == 0 {
+                    reached_top = false;
+                    break 'items;
+                }
+                y -= 1;
+            }
+        }
 
-        msgs.drain(0..msg_diff);
+        if self.scroll.get() > 0 {
+            screen.draw_styled(
+                area.left() + NOTICE_INDENT,
+                area.bottom() - 1,
+                "-- more below --",
+                self.theme.scroll_marker,
+            );
+        }
+        if reached_top {
+            self.load_history(context);
+        }
+        Ok(())
+    }
 
-        let mut messages = msgs.clone();
+    /// Wraps a message into the lines it is drawn as
+    fn item_lines(&self, item: &MessageItem, area: Rect) -> Vec<Vec<Span>> {
+        match item {
+            MessageItem::DiscordMessage(msg) => {
+                let mut content = Vec::new();
+                let resolver = MentionResolver {
+                    messages: self,
+                    guild_id: utils::guild_id(msg),
+                };
+                content.extend(spans::from_markdown(
+                    &msg.content,
+                    &resolver,
+                    self.char_set.quote_bar(),
+                ));
 
-        let mut y = size.height.saturating_sub(BOTTOM_DIFF + 1);
-        for mut msg in messages.iter_mut().rev() {
-            match msg {
-                MessageItem::DiscordMessage(msg) => {
-                    if !self.render_discord_msg(msg, &mut y, size, screen, context)? {
-                        break;
+                let width = area
+                    .width
+                    .saturating_sub(RIGHT_PADDING + LEFT_PADDING + TIME_PADDING);
+                let mut lines = spans::wrap(&content, width);
+                let has_extras = !msg.attachments.is_empty() || !msg.embeds.is_empty();
+                if msg.content.is_empty() && has_extras {
+                    lines.clear();
+                }
+                for attachment in &msg.attachments {
+                    let text = format!(
+                        "{} {} ({})",
+                        self.char_set.paper_clip(),
+                        attachment.filename,
+                        human_size(attachment.size)
+                    );
+                    let attrs = Attributes {
+                        link: true,
+                        ..Attributes::default()
                     };
+                    lines.extend(spans::wrap(&[Span::new(text, attrs)], width));
+                }
+                for embed in &msg.embeds {
+                    lines.extend(embed_lines(embed, &resolver, &self.char_set, width));
                 }
+                if !msg.reactions.is_empty() {
+                    lines.extend(spans::wrap(&reaction_spans(msg), width));
+                }
+                lines
             }
+            MessageItem::Notice(text) => spans::wrap(
+                &[Span::plain(text.to_owned())],
+                area.width.saturating_sub(RIGHT_PADDING + NOTICE_INDENT),
+            ),
         }
-        Ok(())
     }
 
-    fn render_discord_msg(
+    /// Draws line `index` of a message, along with the timestamp and nick on the first line
+    fn draw_line(
         &self,
-        msg: &mut channel::Message,
-        y: &mut usize,
-        size: TermSize,
+        item: &MessageItem,
+        index: usize,
+        line: &[Span],
+        area: Rect,
+        row: usize,
         screen: &mut Terminal,
-        context: &Arc<RwLock<Context>>,
-    ) -> Result<bool, io::Error> {
-        // Show an indicator if an attachement is present
-        let content = if !msg.attachments.is_empty() {
-            format!("{} {}", context.read().char_set.paper_clip(), msg.content)
-        } else {
-            msg.content.to_owned()
-        };
-
-        let left_start = if self.showing_sidebar() {
-            LEFT_START_EXTENDED
-        } else {
-            LEFT_START
-        };
-
-        let wrapped_lines: Vec<String> = content
-            .lines()
-            .map(|line| {
-                fill(
-                    line,
-                    (size.width as usize)
-                        .saturating_sub(RIGHT_PADDING + LEFT_PADDING + left_start + TIME_PADDING),
-                )
-            })
-            .collect();
-        msg.content = wrapped_lines.join("\n");
-
-        let lines: Vec<_> = msg.content.lines().rev().collect();
-        for (i, line) in lines.iter().enumerate() {
-            if i == (lines.len() - 1) {
-                let timestamp = msg
-                    .timestamp
-                    .with_timezone(&::chrono::offset::Local)
-                    .format(&self.timestamp_fmt)
-                    .to_string();
-                let timestamp_len = timestamp.len();
-                let timestamp = timestamp + &if msg.edited_timestamp.is_some() {
-                    "*"
+    ) {
+        let x = area.left();
+        let y = area.top() + row;
+        match item {
+            MessageItem::DiscordMessage(msg) => {
+                let background = if Some(msg.id) == self.selected.get() {
+                    self.theme.selected_message.bg
                 } else {
-                    ""
+                    None
                 };
-                self.put_nick(&msg, screen, left_start + timestamp_len + 1, *y + TOP_START);
-                screen
-                    .buf
-                    .string_builder(left_start.saturating_sub(2), *y + TOP_START, &timestamp)
-                    .style(Style::Faint)
-                    .draw();
+                if index == 0 {
+                    let timestamp = msg
+                        .timestamp
+                        .with_timezone(&::chrono::offset::Local)
+                        .format(&self.timestamp_fmt)
+                        .to_string();
+                    let timestamp_len = timestamp.len();
+                    let timestamp = timestamp + &if msg.edited_timestamp.is_some() {
+                        "*"
+                    } else {
+                        ""
+                    };
+                    self.put_nick(
+                        &msg,
+                        screen,
+                        x + timestamp_len + TIME_PADDING,
+                        y,
+                        background,
+                    );
+                    let mut look = self.theme.timestamp;
+                    look.bg = background.or(look.bg);
+                    screen.draw_styled(x, y, &timestamp, look);
+                }
+                spans::draw(
+                    screen,
+                    x + NOTICE_INDENT + 10 + *self.max_name_len.borrow(),
+                    y,
+                    line,
+                    self.truecolor,
+                    background,
+                    &self.theme,
+                );
             }
-            screen.buf.print(
-                10 + left_start + *self.max_name_len.borrow(),
-                *y + TOP_START,
-                line,
-            );
-            if *y == 0 {
-                return Ok(false);
+            MessageItem::Notice(_) => {
+                let text: String = line.iter().map(|span| span.text.as_str()).collect();
+                screen.draw_styled(x + NOTICE_INDENT, y, &text, self.theme.notice);
             }
-            *y -= 1;
         }
-        Ok(true)
     }
 }
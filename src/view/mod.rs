@@ -1,13 +1,17 @@
+mod completion;
 mod guild_list;
+mod header;
 mod indicator;
 mod input;
 mod messages;
+mod spans;
+mod switcher;
 mod terminal;
 
 use std::sync::mpsc::{self, SyncSender};
 use std::sync::Arc;
 
-use model::Context;
+use model::{Context, History, Layout, Rect};
 
 use serenity::prelude::RwLock;
 
@@ -23,11 +27,12 @@ pub struct View {
     pub terminal_size: termbuf::TermSize,
     pub indicator: indicator::Indicator,
     pub guild_list: guild_list::GuildList,
+    pub switcher: switcher::Switcher,
     pub context: Arc<RwLock<Context>>,
 }
 
 impl View {
-    pub fn new(context: &Arc<RwLock<Context>>) -> View {
+    pub fn new(context: &Arc<RwLock<Context>>, history: History) -> View {
         let locked_ctx = context.read();
 
         let terminal = terminal::Terminal::new().unwrap();
@@ -37,10 +42,25 @@ impl View {
         let (killswitch_tx, killswitch_rx) = mpsc::sync_channel(0);
         terminal.listen(locked_ctx.event_channel.clone(), killswitch_rx);
 
-        let message_view = messages::Messages::new(locked_ctx.timestamp_fmt.clone(), false);
-        let input_view = input::Input::new(locked_ctx.event_channel.clone());
+        let message_view = messages::Messages::new(
+            locked_ctx.timestamp_fmt.clone(),
+            locked_ctx.char_set.clone(),
+            locked_ctx.theme.clone(),
+            locked_ctx.event_channel.clone(),
+        );
+        let input_view = input::Input::new(
+            locked_ctx.event_channel.clone(),
+            locked_ctx.input_rows,
+            history,
+            locked_ctx.channel,
+            locked_ctx.keymap.clone(),
+        );
         let indicator = indicator::Indicator::new(locked_ctx.event_channel.clone());
-        let guild_list = guild_list::GuildList::new();
+        let guild_list = guild_list::GuildList::new(
+            locked_ctx.char_set.clone(),
+            locked_ctx.event_channel.clone(),
+        );
+        let switcher = switcher::Switcher::new(locked_ctx.event_channel.clone());
 
         View {
             terminal,
@@ -50,6 +70,7 @@ impl View {
             terminal_size,
             indicator,
             guild_list,
+            switcher,
             context: context.clone(),
         }
     }
@@ -57,26 +78,42 @@ impl View {
     pub fn present(&mut self) -> Result<(), Error> {
         self.terminal.buf.clear()?;
 
-        self.message_view.render(
-            &mut self.terminal,
-            self.terminal_size,
-            &self.context.clone(),
-        )?;
+        let layout = self.layout();
+        let context = self.context.clone();
+        header::render(&mut self.terminal, layout.header, &context);
+        self.message_view
+            .render(&mut self.terminal, layout.messages, &context)?;
         self.input_view
-            .render(&mut self.terminal, self.terminal_size);
-        self.indicator
-            .render(&mut self.terminal, self.terminal_size);
-        if self.message_view.showing_sidebar() {
-            self.guild_list.render(
+            .render(&mut self.terminal, layout.input, &context);
+        self.indicator.render(&mut self.terminal, layout.indicator);
+        if let Some(sidebar) = layout.sidebar {
+            self.guild_list
+                .render(&mut self.terminal, sidebar, &context);
+        }
+        if self.switcher.is_open() {
+            // Open over the channel rather than the sidebar
+            self.switcher.render(
                 &mut self.terminal,
-                self.terminal_size,
-                &self.context.clone(),
+                layout.header.union(&layout.messages),
+                &context,
             );
         }
         self.terminal.buf.flush()?;
         Ok(())
     }
 
+    /// Splits the terminal into panes, the input box growing with the lines being composed
+    fn layout(&self) -> Layout {
+        let context = self.context.read();
+        let screen = Rect::new(0, 0, self.terminal_size.width, self.terminal_size.height);
+        let sidebar_width = if context.guild_sidebar_visible {
+            Some(context.sidebar_width)
+        } else {
+            None
+        };
+        Layout::new(screen, sidebar_width, self.input_view.rows())
+    }
+
     pub fn update_size(&mut self) {
         self.terminal_size = self
             .terminal
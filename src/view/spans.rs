@@ -0,0 +1,487 @@
+use parsing::markdown::Node;
+use parsing::parse_msg;
+use serenity::model::id::{ChannelId, RoleId, UserId};
+use serenity::utils::Colour;
+use termbuf::Color;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use model::{Look, Theme};
+use view::terminal::Terminal;
+
+fn color_to_8bit(colour: Colour) -> Color {
+    let r = (u16::from(colour.r()) * 5 / 255) as u8;
+    let g = (u16::from(colour.g()) * 5 / 255) as u8;
+    let b = (u16::from(colour.b()) * 5 / 255) as u8;
+    Color::AnsiValue(16 + 36 * r + 6 * g + b)
+}
+
+/// Converts a Discord colour to a terminal colour, approximating it without truecolor support
+pub fn terminal_colour(colour: Colour, truecolor: bool) -> Color {
+    if truecolor {
+        Color::Rgb(colour.r(), colour.g(), colour.b())
+    } else {
+        color_to_8bit(colour)
+    }
+}
+
+/// Looks up the display text of mention and timestamp tokens
+pub trait Resolver {
+    fn user(&self, user_id: UserId) -> String;
+    fn channel(&self, channel_id: ChannelId) -> String;
+    fn role(&self, role_id: RoleId) -> (String, Option<Colour>);
+    fn timestamp(&self, seconds: i64, style: Option<char>) -> String;
+}
+
+/// Attributes a span of text is drawn with
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Attributes {
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub code: bool,
+    pub spoiler: bool,
+    pub link: bool,
+    pub quote: bool,
+    pub mention: bool,
+    pub colour: Option<Colour>,
+}
+
+/// A run of text drawn with a single set of attributes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub attrs: Attributes,
+}
+
+impl Span {
+    pub fn new(text: String, attrs: Attributes) -> Span {
+        Span { text, attrs }
+    }
+
+    pub fn plain(text: String) -> Span {
+        Span::new(text, Attributes::default())
+    }
+
+    /// Columns the text takes up on screen
+    pub fn width(&self) -> usize {
+        self.text.width()
+    }
+}
+
+/// Converts a Discord message into spans, falling back to plain text if it cannot be parsed
+pub fn from_markdown(content: &str, resolver: &Resolver, quote_bar: &str) -> Vec<Span> {
+    let nodes = match parse_msg(content) {
+        Some(nodes) => nodes,
+        None => return vec![Span::plain(content.to_owned())],
+    };
+
+    let mut spans = Vec::new();
+    flatten(
+        nodes,
+        Attributes::default(),
+        resolver,
+        quote_bar,
+        &mut spans,
+    );
+    spans
+}
+
+/// Starts a new line unless the spans are empty or already end with one
+fn line_break(spans: &mut Vec<Span>) {
+    if spans.last().map_or(false, |span| !span.text.ends_with('\n')) {
+        spans.push(Span::plain("\n".to_owned()));
+    }
+}
+
+fn flatten(
+    nodes: Vec<Node>,
+    attrs: Attributes,
+    resolver: &Resolver,
+    quote_bar: &str,
+    spans: &mut Vec<Span>,
+) {
+    // Anything following a block starts on its own line
+    let mut block_ended = false;
+    for mut node in nodes {
+        if block_ended {
+            block_ended = false;
+            line_break(spans);
+            if let Node::Text(ref mut text) = node {
+                if text.starts_with('\n') {
+                    text.remove(0);
+                }
+            }
+        }
+
+        let mut inner = attrs;
+        match node {
+            Node::Text(text) => spans.push(Span::new(text, attrs)),
+            Node::Code(code) => {
+                inner.code = true;
+                spans.push(Span::new(code, inner));
+            }
+            Node::CodeBlock { code, .. } => {
+                inner.code = true;
+                line_break(spans);
+                spans.push(Span::new(code, inner));
+                block_ended = true;
+            }
+            Node::Bold(children) => {
+                inner.bold = true;
+                flatten(children, inner, resolver, quote_bar, spans);
+            }
+            Node::Italic(children) => {
+                inner.italic = true;
+                flatten(children, inner, resolver, quote_bar, spans);
+            }
+            Node::Underline(children) => {
+                inner.underline = true;
+                flatten(children, inner, resolver, quote_bar, spans);
+            }
+            Node::Strikethrough(children) => {
+                inner.strikethrough = true;
+                flatten(children, inner, resolver, quote_bar, spans);
+            }
+            Node::Spoiler(children) => {
+                inner.spoiler = true;
+                flatten(children, inner, resolver, quote_bar, spans);
+            }
+            Node::BlockQuote(children) => {
+                let mut quoted = Vec::new();
+                flatten(children, attrs, resolver, quote_bar, &mut quoted);
+
+                let mut bar = Attributes::default();
+                bar.quote = true;
+                line_break(spans);
+                spans.push(Span::new(quote_bar.to_owned(), bar));
+                for span in quoted {
+                    let mut lines = span.text.split('\n');
+                    if let Some(first) = lines.next() {
+                        spans.push(Span::new(first.to_owned(), span.attrs));
+                    }
+                    for line in lines {
+                        spans.push(Span::plain("\n".to_owned()));
+                        spans.push(Span::new(quote_bar.to_owned(), bar));
+                        spans.push(Span::new(line.to_owned(), span.attrs));
+                    }
+                }
+                block_ended = true;
+            }
+            Node::Link { text, url } => {
+                inner.link = true;
+                if text == url {
+                    spans.push(Span::new(url, inner));
+                } else {
+                    spans.push(Span::new(text, inner));
+                    spans.push(Span::new(format!(" ({})", url), attrs));
+                }
+            }
+            Node::UserMention(id) => {
+                inner.mention = true;
+                spans.push(Span::new(resolver.user(UserId(id)), inner));
+            }
+            Node::ChannelMention(id) => {
+                inner.mention = true;
+                spans.push(Span::new(resolver.channel(ChannelId(id)), inner));
+            }
+            Node::RoleMention(id) => {
+                let (name, colour) = resolver.role(RoleId(id));
+                inner.mention = true;
+                inner.colour = colour;
+                spans.push(Span::new(name, inner));
+            }
+            Node::Timestamp { seconds, style } => {
+                inner.mention = true;
+                spans.push(Span::new(resolver.timestamp(seconds, style), inner));
+            }
+        }
+    }
+}
+
+/// Splits text into alternating runs of whitespace and non-whitespace
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut last_space = None;
+    for (i, ch) in text.char_indices() {
+        let space = ch.is_whitespace();
+        if last_space.map_or(false, |last| last != space) {
+            words.push(&text[start..i]);
+            start = i;
+        }
+        last_space = Some(space);
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+/// Appends text to a line, merging it into the previous span when the attributes match
+fn push(line: &mut Vec<Span>, text: &str, attrs: Attributes) {
+    if let Some(last) = line.last_mut() {
+        if last.attrs == attrs {
+            last.text.push_str(text);
+            return;
+        }
+    }
+    line.push(Span::new(text.to_owned(), attrs));
+}
+
+/// Ends a soft wrapped line, dropping whitespace left at its end
+fn soft_break(lines: &mut Vec<Vec<Span>>) {
+    if let Some(line) = lines.last_mut() {
+        while let Some(mut last) = line.pop() {
+            let trimmed = last.text.trim_right().len();
+            if trimmed > 0 {
+                last.text.truncate(trimmed);
+                line.push(last);
+                break;
+            }
+        }
+    }
+    lines.push(Vec::new());
+}
+
+/// A piece of text wrapping treats as a unit
+enum Token<'a> {
+    Newline,
+    /// A run of whitespace or of other characters, which may cross spans with different attributes
+    Run {
+        space: bool,
+        parts: Vec<(&'a str, Attributes)>,
+    },
+}
+
+/// Splits spans into words, runs of whitespace and line breaks
+fn tokens<'a>(spans: &'a [Span]) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+    for span in spans {
+        for (i, segment) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                tokens.push(Token::Newline);
+            }
+            for word in words(segment) {
+                let space = word.chars().all(char::is_whitespace);
+                // A word continues into the next span when nothing separates them
+                let continues = match tokens.last() {
+                    Some(Token::Run { space: last, .. }) => *last == space,
+                    _ => false,
+                };
+                if continues {
+                    if let Some(Token::Run { ref mut parts, .. }) = tokens.last_mut() {
+                        parts.push((word, span.attrs));
+                    }
+                } else {
+                    tokens.push(Token::Run {
+                        space,
+                        parts: vec![(word, span.attrs)],
+                    });
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// Word-wraps spans to `width` columns, splitting spans at line breaks without losing their style
+pub fn wrap(spans: &[Span], width: usize) -> Vec<Vec<Span>> {
+    let width = width.max(1);
+    let mut lines = vec![Vec::new()];
+    let mut line_width = 0;
+    // Whitespace at the start of a soft wrapped line is dropped
+    let mut soft_wrapped = false;
+
+    for token in tokens(spans) {
+        let (space, parts) = match token {
+            Token::Newline => {
+                lines.push(Vec::new());
+                line_width = 0;
+                soft_wrapped = false;
+                continue;
+            }
+            Token::Run { space, parts } => (space, parts),
+        };
+        let run_width: usize = parts.iter().map(|(text, _)| text.width()).sum();
+
+        if line_width > 0 && line_width + run_width > width {
+            soft_break(&mut lines);
+            line_width = 0;
+            soft_wrapped = true;
+        }
+        if space && line_width == 0 && soft_wrapped {
+            continue;
+        }
+
+        if run_width <= width {
+            for (text, attrs) in parts {
+                push(lines.last_mut().unwrap(), text, attrs);
+            }
+            line_width += run_width;
+            continue;
+        }
+        // Words longer than a line are split wherever the line ends
+        for (text, attrs) in parts {
+            let mut start = 0;
+            for (i, ch) in text.char_indices() {
+                let ch_width = ch.width().unwrap_or(0);
+                if line_width > 0 && line_width + ch_width > width {
+                    if start < i {
+                        push(lines.last_mut().unwrap(), &text[start..i], attrs);
+                    }
+                    soft_break(&mut lines);
+                    start = i;
+                    line_width = 0;
+                    soft_wrapped = true;
+                }
+                line_width += ch_width;
+            }
+            if start < text.len() {
+                push(lines.last_mut().unwrap(), &text[start..], attrs);
+            }
+        }
+    }
+    lines
+}
+
+/// Draws a single wrapped line starting at `x`, optionally over a background colour
+pub fn draw(
+    screen: &mut Terminal,
+    x: usize,
+    y: usize,
+    line: &[Span],
+    truecolor: bool,
+    background: Option<Color>,
+    theme: &Theme,
+) {
+    let mut x = x;
+    for span in line {
+        let mut look = Look {
+            bg: background,
+            bold: span.attrs.bold || span.attrs.mention,
+            faint: span.attrs.faint,
+            italic: span.attrs.italic,
+            underline: span.attrs.underline,
+            strikethrough: span.attrs.strikethrough,
+            ..Look::default()
+        };
+        if span.attrs.code {
+            look = look.merge(theme.code);
+        }
+        if span.attrs.link {
+            look = look.merge(theme.link);
+        }
+        if span.attrs.quote {
+            look = look.merge(theme.quote);
+        }
+        if let Some(colour) = span.attrs.colour {
+            look.fg = Some(terminal_colour(colour, truecolor));
+        }
+        // Spoilers are hidden by drawing them in the same colour as their background
+        if span.attrs.spoiler {
+            look = look.merge(theme.spoiler);
+        }
+        screen.draw_styled(x, y, &span.text, look);
+        x += span.width();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bold() -> Attributes {
+        Attributes {
+            bold: true,
+            ..Attributes::default()
+        }
+    }
+
+    #[test]
+    fn wrap_keeps_styles() {
+        let spans = vec![
+            Span::plain("one ".to_owned()),
+            Span::new("two three".to_owned(), bold()),
+        ];
+
+        assert_eq!(
+            wrap(&spans, 8),
+            vec![
+                vec![
+                    Span::plain("one ".to_owned()),
+                    Span::new("two".to_owned(), bold()),
+                ],
+                vec![Span::new("three".to_owned(), bold())],
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_hard_breaks() {
+        let spans = vec![Span::plain("a\nb".to_owned())];
+
+        assert_eq!(
+            wrap(&spans, 80),
+            vec![
+                vec![Span::plain("a".to_owned())],
+                vec![Span::plain("b".to_owned())],
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_long_word() {
+        let spans = vec![Span::plain("abcdefg".to_owned())];
+
+        assert_eq!(
+            wrap(&spans, 3),
+            vec![
+                vec![Span::plain("abc".to_owned())],
+                vec![Span::plain("def".to_owned())],
+                vec![Span::plain("g".to_owned())],
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_keeps_words_across_spans() {
+        let spans = vec![
+            Span::plain("one foo".to_owned()),
+            Span::new("bar".to_owned(), bold()),
+        ];
+
+        assert_eq!(
+            wrap(&spans, 8),
+            vec![
+                vec![Span::plain("one".to_owned())],
+                vec![
+                    Span::plain("foo".to_owned()),
+                    Span::new("bar".to_owned(), bold()),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_measures_wide_characters() {
+        let spans = vec![Span::plain("日本語 ok".to_owned())];
+
+        assert_eq!(Span::plain("日本語".to_owned()).width(), 6);
+        assert_eq!(
+            wrap(&spans, 7),
+            vec![
+                vec![Span::plain("日本語".to_owned())],
+                vec![Span::plain("ok".to_owned())],
+            ]
+        );
+        assert_eq!(
+            wrap(&[Span::plain("日本語".to_owned())], 4),
+            vec![
+                vec![Span::plain("日本".to_owned())],
+                vec![Span::plain("語".to_owned())],
+            ]
+        );
+    }
+}
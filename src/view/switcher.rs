@@ -0,0 +1,141 @@
+use model::{Context, Event, Look, Rect};
+use view::terminal::Terminal;
+
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use serenity::model::id::ChannelId;
+use serenity::prelude::RwLock;
+use termbuf::termion::event::Key;
+
+use failure::Error;
+
+use helpers::fuzzy;
+
+const MAX_WIDTH: usize = 70;
+const MAX_RESULTS: usize = 12;
+
+/// A Ctrl-K popup that fuzzy-matches every reachable channel
+pub struct Switcher {
+    open: bool,
+    query: String,
+    /// Every channel that can be switched to, with the text it is matched against
+    entries: Vec<(String, ChannelId)>,
+    /// Indices into `entries`, best match first
+    matches: Vec<usize>,
+    selected: usize,
+    event_channel: Sender<Event>,
+}
+
+impl Switcher {
+    pub fn new(event_channel: Sender<Event>) -> Switcher {
+        Switcher {
+            open: false,
+            query: String::new(),
+            entries: Vec::new(),
+            matches: Vec::new(),
+            selected: 0,
+            event_channel,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, entries: Vec<(String, ChannelId)>) {
+        self.open = true;
+        self.query.clear();
+        self.entries = entries;
+        self.update_matches();
+    }
+
+    fn update_matches(&mut self) {
+        let mut scored: Vec<_> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (label, _))| {
+                fuzzy::score(&self.query, label).map(|score| (score, label.len(), i))
+            })
+            .collect();
+        // Best score first, preferring shorter labels on ties
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        self.matches = scored.into_iter().map(|(_, _, i)| i).collect();
+        self.selected = 0;
+    }
+
+    pub fn key_press(&mut self, key: Key) -> Result<(), Error> {
+        match key {
+            Key::Esc | Key::Ctrl('k') => self.open = false,
+            Key::Char('\n') => {
+                if let Some(&index) = self.matches.get(self.selected) {
+                    self.event_channel
+                        .send(Event::SetChannel(self.entries[index].1))?;
+                }
+                self.open = false;
+            }
+            Key::Up | Key::Ctrl('p') => self.selected = self.selected.saturating_sub(1),
+            Key::Down | Key::Ctrl('n') => {
+                self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1))
+            }
+            Key::Backspace => {
+                self.query.pop();
+                self.update_matches();
+            }
+            Key::Ctrl('u') => {
+                self.query.clear();
+                self.update_matches();
+            }
+            Key::Char(ch) => {
+                self.query.push(ch);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Draws the popup centred at the top of `area`
+    pub fn render(&self, screen: &mut Terminal, area: Rect, context: &Arc<RwLock<Context>>) {
+        let context = context.read();
+        let width = MAX_WIDTH.min(area.width.saturating_sub(4));
+        let results = MAX_RESULTS.min(area.height.saturating_sub(6));
+        let x = area.left() + (area.width - width) / 2;
+        let y = area.top() + 2;
+        let blank = " ".repeat(width);
+
+        // Blank out whatever is underneath the popup
+        for row in 0..results + 2 {
+            screen.buf.print(x, y + row, &blank);
+        }
+        screen.draw_box(
+            x.saturating_sub(1),
+            y.saturating_sub(1),
+            width,
+            results + 2,
+            context.char_set.box_chars(),
+            context.theme.border,
+        );
+
+        let prompt: String = format!("> {}", self.query).chars().take(width).collect();
+        screen.buf.print(x, y, &prompt);
+
+        // Keep the selection visible when it moves past the last row
+        let start = (self.selected + 1).saturating_sub(results);
+        for (row, &index) in self.matches.iter().skip(start).take(results).enumerate() {
+            let label: String = self.entries[index].0.chars().take(width).collect();
+            let look = if start + row == self.selected {
+                context.theme.cursor
+            } else {
+                Look::default()
+            };
+            screen.draw_styled(x, y + 2 + row, &label, look);
+        }
+
+        screen
+            .buf
+            .set_cursor_position(x + prompt.chars().count() + 1, y + 1);
+    }
+}
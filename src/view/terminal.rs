@@ -4,9 +4,10 @@ use std::time::Duration;
 
 use termbuf::termion::async_stdin;
 use termbuf::termion::input::TermRead;
-use termbuf::TermBuf;
+use termbuf::{Style, TermBuf};
 
-use model::Event;
+use helpers::chars::BoxChars;
+use model::{Event, Look};
 
 use failure::Error;
 
@@ -23,6 +24,67 @@ impl Terminal {
         Ok(term)
     }
 
+    /// Draws text with a theme look
+    pub fn draw_styled(&mut self, x: usize, y: usize, text: &str, look: Look) {
+        let mut builder = self.buf.string_builder(x, y, text);
+        if let Some(fg) = look.fg {
+            builder = builder.fg(fg);
+        }
+        if let Some(bg) = look.bg {
+            builder = builder.bg(bg);
+        }
+        if look.bold {
+            builder = builder.style(Style::Bold);
+        }
+        if look.faint {
+            builder = builder.style(Style::Faint);
+        }
+        if look.italic {
+            builder = builder.style(Style::Italic);
+        }
+        if look.underline {
+            builder = builder.style(Style::Underline);
+        }
+        if look.strikethrough {
+            builder = builder.style(Style::CrossedOut);
+        }
+        builder.draw();
+    }
+
+    /// Draws the outline of a box around a `width` by `height` area starting one cell in from x, y
+    pub fn draw_box(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        chars: BoxChars,
+        look: Look,
+    ) {
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = chars;
+        let edge = horizontal.to_string().repeat(width);
+        let top = format!("{}{}{}", top_left, edge, top_right);
+        let bottom = format!("{}{}{}", bottom_left, edge, bottom_right);
+        self.draw_styled(x, y, &top, look);
+        self.draw_vertical_line(x, y + 1, height, vertical, look);
+        self.draw_vertical_line(x + width + 1, y + 1, height, vertical, look);
+        self.draw_styled(x, y + height + 1, &bottom, look);
+    }
+
+    pub fn draw_vertical_line(
+        &mut self,
+        x: usize,
+        y: usize,
+        height: usize,
+        line: char,
+        look: Look,
+    ) {
+        let line = line.to_string();
+        for row in y..y + height {
+            self.draw_styled(x, row, &line, look);
+        }
+    }
+
     pub fn listen(&self, event_channel: Sender<Event>, killswitch: Receiver<()>) {
         thread::spawn(move || loop {
             let mut keys = async_stdin().keys();